@@ -0,0 +1,129 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Component, Path};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tar::{Archive, Builder, Header};
+
+/// Kind of the file stored in a `<guid>/` directory of the package.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum EntryKind {
+    Pathname,
+    Asset,
+    Meta,
+    Preview,
+    /// The `<guid>/` directory entry itself.
+    Directory,
+    Other,
+}
+
+impl EntryKind {
+    fn from_file_name(file_name: &str) -> EntryKind {
+        match file_name {
+            "pathname" => EntryKind::Pathname,
+            "asset" => EntryKind::Asset,
+            "asset.meta" => EntryKind::Meta,
+            "preview.png" => EntryKind::Preview,
+            _ => EntryKind::Other,
+        }
+    }
+}
+
+pub(crate) fn open(archive_path: &Path) -> io::Result<Archive<GzDecoder<File>>> {
+    let tar_gz = File::open(archive_path)?;
+    Ok(Archive::new(GzDecoder::new(tar_gz)))
+}
+
+pub(crate) fn create(archive_path: &Path) -> io::Result<Builder<GzEncoder<File>>> {
+    let file = File::create(archive_path)?;
+    Ok(Builder::new(GzEncoder::new(file, Compression::default())))
+}
+
+/// Flushes the tar and gzip streams.
+pub(crate) fn finish(builder: Builder<GzEncoder<File>>) -> io::Result<()> {
+    builder.into_inner()?.finish()?.flush()
+}
+
+/// Appends in-memory file as `<guid>/<file_name>` entry.
+pub(crate) fn append_bytes<W: Write>(
+    builder: &mut Builder<W>,
+    guid: &str,
+    file_name: &str,
+    data: &[u8],
+) -> io::Result<()> {
+    let mut header = Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    header.set_mtime(now);
+    header.set_cksum();
+    builder.append_data(&mut header, format!("{}/{}", guid, file_name), data)
+}
+
+/// Splits `<guid>/<file>` tar entry path into its parts.
+pub(crate) fn split_entry_path(path: &Path) -> Option<(String, EntryKind)> {
+    let mut parts = path.components().filter_map(|c| match c {
+        Component::Normal(part) => part.to_str(),
+        _ => None,
+    });
+    let guid = parts.next()?.to_string();
+    let kind = match parts.next() {
+        Some(file_name) => EntryKind::from_file_name(file_name),
+        None => EntryKind::Directory,
+    };
+    Some((guid, kind))
+}
+
+/// Reads the first line of the `pathname` entry, the rest of the file is not used by Unity.
+///
+/// Absolute pathnames and the ones leaving the package root (`..`) are reported and skipped,
+/// they would be written outside of the output directory.
+pub(crate) fn read_pathname<R: io::Read>(reader: R) -> io::Result<Option<String>> {
+    let content = io::read_to_string(reader)?;
+    let Some(pathname) = content.lines().next() else {
+        return Ok(None);
+    };
+    if !is_relative_pathname(pathname) {
+        eprintln!("Skipping asset with invalid pathname {:?}", pathname);
+        return Ok(None);
+    }
+    Ok(Some(pathname.to_string()))
+}
+
+/// Pathname made only of regular components, staying inside of the directory it is joined to.
+fn is_relative_pathname(pathname: &str) -> bool {
+    let mut components = Path::new(pathname).components().peekable();
+    components.peek().is_some() && components.all(|c| matches!(c, Component::Normal(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pathname(content: &str) -> Option<String> {
+        read_pathname(content.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn reads_first_line() {
+        assert_eq!(
+            pathname("Assets/Models/Rock.fbx\n00"),
+            Some("Assets/Models/Rock.fbx".to_string())
+        );
+        assert_eq!(pathname(""), None);
+    }
+
+    #[test]
+    fn rejects_hostile_pathnames() {
+        assert_eq!(pathname("../../x"), None);
+        assert_eq!(pathname("Assets/../../x"), None);
+        assert_eq!(pathname("./Assets/x"), None);
+        assert_eq!(pathname("/etc/passwd"), None);
+        assert_eq!(pathname("/"), None);
+    }
+}
@@ -1,9 +1,155 @@
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
+use lwa_unity_unpack::convert::mapping::ShaderMappings;
+use lwa_unity_unpack::filter::{AssetFilter, PathPattern};
+use lwa_unity_unpack::{AssetType, UnpackOptions};
 use std::path::PathBuf;
 
 /// Program for unpacking unitypackages files.
 #[derive(Parser, Debug, Clone)]
 #[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// unpacking arguments, used when no subcommand is given
+    #[command(flatten)]
+    pub unpack: Option<Args>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Unpack the package into target directory
+    Unpack(Args),
+    /// Print the package content without extracting it
+    #[command(alias = "info")]
+    List(ListArgs),
+    /// Pack a directory with assets into a new .unitypackage
+    Pack(PackArgs),
+    /// Copy selected entries of the package into a new .unitypackage
+    Repack(RepackArgs),
+    /// Compare two versions of the same package by GUID
+    Diff(DiffArgs),
+    /// Print GUID references between the package assets
+    Graph(GraphArgs),
+    /// List unused assets and references pointing outside of the package
+    Orphans(ListArgs),
+    /// List shaders and render pipeline used by the package materials
+    Shaders(ListArgs),
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ListArgs {
+    /// .unitypackage file to inspect
+    #[arg(short, long)]
+    pub input: PathBuf,
+
+    /// output format
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GraphFormat {
+    #[default]
+    Json,
+    Dot,
+    Mermaid,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct GraphArgs {
+    /// .unitypackage file to inspect
+    #[arg(short, long)]
+    pub input: PathBuf,
+
+    /// output format
+    #[arg(long, value_enum, default_value_t)]
+    pub format: GraphFormat,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct DiffArgs {
+    /// previous version of the .unitypackage
+    pub old: PathBuf,
+
+    /// new version of the .unitypackage
+    pub new: PathBuf,
+
+    /// output format
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct PackArgs {
+    /// directory with assets, pathnames in package are relative to it
+    #[arg(short, long)]
+    pub input: PathBuf,
+
+    /// .unitypackage file to create
+    #[arg(short, long)]
+    pub output: PathBuf,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct RepackArgs {
+    /// .unitypackage file to read
+    #[arg(short, long)]
+    pub input: PathBuf,
+
+    /// .unitypackage file to create
+    #[arg(short, long)]
+    pub output: PathBuf,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct FilterArgs {
+    /// pathname patterns to include, supports `*`, `**` and `?`, folder includes everything inside
+    #[arg(long, action = clap::ArgAction::Append)]
+    pub include: Vec<PathPattern>,
+
+    /// pathname patterns to exclude
+    #[arg(long, action = clap::ArgAction::Append)]
+    pub exclude: Vec<PathPattern>,
+
+    /// asset types to include, e.g. `material`, `prefab`, `fbx_model` or an extension
+    #[arg(long, action = clap::ArgAction::Append)]
+    pub include_type: Vec<AssetType>,
+
+    /// asset types to exclude
+    #[arg(long, action = clap::ArgAction::Append)]
+    pub exclude_type: Vec<AssetType>,
+
+    /// extensions that will be ignored
+    #[arg(long, action = clap::ArgAction::Append)]
+    pub ignore_extensions: Vec<String>,
+}
+
+impl From<FilterArgs> for AssetFilter {
+    fn from(args: FilterArgs) -> Self {
+        AssetFilter {
+            include: args.include,
+            exclude: args.exclude,
+            include_types: args.include_type,
+            exclude_types: args.exclude_type,
+            ignore_extensions: args.ignore_extensions,
+            guids: None,
+        }
+    }
+}
+
+#[derive(clap::Args, Debug, Clone)]
 pub struct Args {
     /// .unitypackage file to extract
     #[arg(short, long)]
@@ -28,17 +174,58 @@ pub struct Args {
     /// copy meta files alongside regular files
     #[arg(long, default_value = "false", default_missing_value = "true")]
     pub copy_meta_files: bool,
+
+    /// optional- unpack only assets matching the pathname (e.g. scene or prefab) and everything they reference
+    #[arg(long, action = clap::ArgAction::Append)]
+    pub root: Vec<PathPattern>,
+
+    /// skip assets that are not used by any scene or prefab (materials and models in packages without them)
+    #[arg(long, default_value = "false", default_missing_value = "true")]
+    pub skip_unreferenced: bool,
+
+    /// optional- TOML or JSON file mapping shader properties to glTF material slots
+    #[arg(long)]
+    pub shader_mapping: Option<PathBuf>,
+
+    /// write materials.json listing the package materials with their shaders, textures and properties
+    #[arg(long, default_value = "false", default_missing_value = "true")]
+    pub export_materials: bool,
+
+    /// write a glb next to every prefab with its hierarchy, meshes and materials, requires fbx_to_gltf
+    #[arg(
+        long,
+        default_value = "false",
+        default_missing_value = "true",
+        requires = "fbx_to_gltf"
+    )]
+    pub export_prefabs: bool,
 }
 
 impl Args {
     pub fn check(&self) {
+        assert!(self.input.exists(), "Input file does not exits");
         if let Some(path) = &self.fbx_to_gltf {
             assert!(
-                is_executable(&path),
+                is_executable(path),
                 "fbx_to_gltf require a path to executable"
             )
         }
     }
+
+    pub fn unpack_options(&self) -> UnpackOptions {
+        UnpackOptions {
+            fbx_to_gltf: self.fbx_to_gltf.clone(),
+            get_materials_from_prefabs: self.get_materials_from_prefabs,
+            filter: AssetFilter {
+                ignore_extensions: self.ignore_extensions.clone().unwrap_or_default(),
+                ..Default::default()
+            },
+            copy_meta_files: self.copy_meta_files,
+            shader_mappings: ShaderMappings::default(),
+            export_materials: self.export_materials,
+            export_prefabs: self.export_prefabs,
+        }
+    }
 }
 
 #[cfg(target_os = "windows")]
@@ -1,19 +1,29 @@
-use crate::primitives::materials::read_single_material;
+use crate::primitives::materials::UnityMaterial;
+use crate::primitives::meta::{read_meta, MetaFile};
+use serde::{Serialize, Serializer};
+use std::collections::HashMap;
 use std::ffi::OsStr;
+use std::fmt;
 use std::fs;
-use std::fs::DirEntry;
-use std::fs::File;
-use std::io::BufRead;
-use std::io::BufReader;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-#[derive(Clone)]
+/// Single GUID entry of the package.
+#[derive(Clone, Debug, Serialize)]
 pub struct Asset {
     pub extension: Option<String>,
     pub guid: String,
-    pub path: String,
+    /// Path of the asset inside the Unity project, e.g. `Assets/Models/Rock.fbx`.
+    pub pathname: String,
+    /// Uncompressed size of the asset body in bytes.
+    pub size: u64,
     pub has_meta: bool,
+    /// Uncompressed size of the meta file in bytes.
+    pub meta_size: u64,
     pub asset_type: AssetType,
+    /// Parsed meta file, `None` when it is missing or could not be parsed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<MetaFile>,
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
@@ -22,34 +32,133 @@ pub enum AssetType {
     Material,
     Prefab,
     Scene,
+    /// Entry that has pathname and meta, but no asset body.
+    Folder,
     Other(String),
 }
 
+impl AssetType {
+    pub fn from_extension(extension: Option<&str>) -> AssetType {
+        match extension {
+            Some(str) => match str {
+                "fbx" => AssetType::FbxModel,
+                "prefab" => AssetType::Prefab,
+                "unity" => AssetType::Scene,
+                "mat" => AssetType::Material,
+                _ => AssetType::Other(str.to_string()),
+            },
+            _ => AssetType::Other(String::new()),
+        }
+    }
+}
+
+impl fmt::Display for AssetType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetType::FbxModel => f.write_str("fbx_model"),
+            AssetType::Material => f.write_str("material"),
+            AssetType::Prefab => f.write_str("prefab"),
+            AssetType::Scene => f.write_str("scene"),
+            AssetType::Folder => f.write_str("folder"),
+            AssetType::Other(ext) if ext.is_empty() => f.write_str("other"),
+            AssetType::Other(ext) => f.write_str(ext),
+        }
+    }
+}
+
+impl FromStr for AssetType {
+    type Err = std::convert::Infallible;
+
+    /// Parses names printed by `Display`, anything else is treated as an extension.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "fbx_model" | "fbx" => AssetType::FbxModel,
+            "material" | "mat" => AssetType::Material,
+            "prefab" => AssetType::Prefab,
+            "scene" | "unity" => AssetType::Scene,
+            "folder" => AssetType::Folder,
+            "other" => AssetType::Other(String::new()),
+            ext => AssetType::Other(ext.to_string()),
+        })
+    }
+}
+
+impl Serialize for AssetType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
 impl Asset {
-    pub fn try_get_mat_texture_guid(&self) -> Option<String> {
+    pub fn new(guid: String, pathname: String) -> Asset {
+        let extension = Path::new(&pathname)
+            .extension()
+            .and_then(OsStr::to_str)
+            .map(String::from);
+        let asset_type = AssetType::from_extension(extension.as_deref());
+        Asset {
+            extension,
+            guid,
+            pathname,
+            size: 0,
+            has_meta: false,
+            meta_size: 0,
+            asset_type,
+            meta: None,
+        }
+    }
+
+    /// Stores the meta file information, parsing the importer settings.
+    pub fn set_meta(&mut self, contents: &str) {
+        self.has_meta = true;
+        self.meta_size = contents.len() as u64;
+        match read_meta(contents) {
+            Ok(meta) => self.meta = Some(meta),
+            Err(e) => eprintln!("Failed to parse meta of {}: {}", self.pathname, e),
+        }
+    }
+
+    /// Path the asset is extracted to inside `output_dir`.
+    pub fn output_path(&self, output_dir: &Path) -> PathBuf {
+        output_dir.join(&self.pathname)
+    }
+
+    /// Path of the glb converted from the model, only the last extension is replaced, so
+    /// `Rock.v2.fbx` becomes `Rock.v2.glb`.
+    pub fn glb_path(&self, output_dir: &Path) -> PathBuf {
+        let mut path = self
+            .output_path(output_dir)
+            .with_extension("")
+            .into_os_string();
+        path.push(".glb");
+        PathBuf::from(path)
+    }
+
+    /// GUID of the `_MainTex` texture, `materials` are parsed materials by GUID with variants
+    /// already resolved (see [`resolve_variants`](crate::primitives::materials::resolve_variants)).
+    pub fn try_get_mat_texture_guid(
+        &self,
+        materials: &HashMap<String, UnityMaterial>,
+    ) -> Option<String> {
         match &self.asset_type {
             AssetType::Material => {}
             _ => return None,
         }
-        let content = fs::read_to_string(&self.path).unwrap();
-        let material = read_single_material(&content);
-        if let Ok(mat) = material {
-            return mat
-                .properties
-                .tex_envs
-                .iter()
-                .find_map(|tex| tex.get("_MainTex"))
-                .and_then(|t| t.texture.guid.clone());
-        }
-        None
+        materials
+            .get(&self.guid)?
+            .properties
+            .tex_envs
+            .iter()
+            .find_map(|tex| tex.get("_MainTex"))
+            .and_then(|t| t.texture.guid.clone())
     }
 
-    pub fn prepare_directory(&self) {
-        println!("{}: {:?}", self.guid, self.path);
-        let base_path = Path::new(&self.path);
-        let result_dir = base_path.parent();
+    pub fn prepare_directory(&self, output_dir: &Path) {
+        let path = self.output_path(output_dir);
+        println!("{}: {:?}", self.guid, path);
+        let result_dir = path.parent();
         if result_dir.is_none() {
-            eprintln!("{} is none", &self.path);
+            eprintln!("{} is none", path.display());
         }
         let result_dir = result_dir.unwrap();
         if !result_dir.exists() {
@@ -63,63 +172,4 @@ impl Asset {
             }
         }
     }
-
-    pub fn from_path(entry: &DirEntry, output_dir: &Path) -> Option<Asset> {
-        let root_file = entry.path();
-        if !root_file.is_dir() {
-            return None;
-        }
-        let guid = entry.file_name().into_string().unwrap();
-        let mut real_path = String::new();
-        let mut extension = None;
-        let mut has_asset = false;
-        let mut has_meta = false;
-        for sub_entry in fs::read_dir(root_file.clone()).unwrap() {
-            let sub_entry = sub_entry.unwrap();
-            let file_name = sub_entry.file_name().into_string().unwrap();
-            match file_name.as_str() {
-                "pathname" => {
-                    let path = sub_entry.path();
-                    let file = File::open(path).unwrap();
-                    let buf_reader = BufReader::new(file);
-                    let line = buf_reader.lines().next();
-                    match line {
-                        Some(Ok(path)) => {
-                            real_path = output_dir.join(path).to_str().unwrap().to_string();
-                            if let Some(e) =
-                                Path::new(&real_path).extension().and_then(OsStr::to_str)
-                            {
-                                extension = Some(String::from(e));
-                            }
-                        }
-                        _ => continue,
-                    }
-                }
-                "asset" => has_asset = true,
-                "asset.meta" => has_meta = true,
-                _ => continue,
-            }
-        }
-        if has_asset {
-            let asset_type = match &extension {
-                Some(str) => match str.as_str() {
-                    "fbx" => AssetType::FbxModel,
-                    "prefab" => AssetType::Prefab,
-                    "unity" => AssetType::Scene,
-                    "mat" => AssetType::Material,
-                    _ => AssetType::Other(str.clone()),
-                },
-                _ => AssetType::Other(String::new()),
-            };
-            Some(Asset {
-                extension,
-                guid,
-                path: real_path,
-                has_meta,
-                asset_type,
-            })
-        } else {
-            None
-        }
-    }
 }
@@ -0,0 +1,49 @@
+use anyhow::{Context, Result};
+use gltf::json;
+use std::borrow::Cow;
+use std::fs;
+use std::path::Path;
+
+/// Binary glTF split into editable JSON and untouched binary chunk.
+pub struct GlbFile {
+    pub json: json::Root,
+    pub blob: Option<Vec<u8>>,
+}
+
+impl GlbFile {
+    pub fn read(path: &Path) -> Result<GlbFile> {
+        let bytes = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        let gltf = gltf::Gltf::from_slice(&bytes)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+        Ok(GlbFile {
+            json: gltf.document.into_json(),
+            blob: gltf.blob,
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json_string = json::serialize::to_string(&self.json)?;
+        let mut json_length = json_string.len();
+        align_to_multiple_of_four(&mut json_length);
+        let blob_length = self.blob.as_ref().map(Vec::len).unwrap_or_default();
+        let glb = gltf::binary::Glb {
+            header: gltf::binary::Header {
+                magic: *b"glTF",
+                version: 2,
+                // N.B., the size of binary glTF file is limited to range of `u32`.
+                length: (json_length + blob_length)
+                    .try_into()
+                    .context("file size exceeds binary glTF limit")?,
+            },
+            bin: self.blob.as_deref().map(Cow::Borrowed),
+            json: Cow::Owned(json_string.into_bytes()),
+        };
+        let writer = fs::File::create(path)?;
+        glb.to_writer(writer)?;
+        Ok(())
+    }
+}
+
+fn align_to_multiple_of_four(n: &mut usize) {
+    *n = (*n + 3) & !3;
+}
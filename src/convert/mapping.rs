@@ -0,0 +1,328 @@
+use crate::primitives::reference::FileReference;
+use crate::shader::BUILTIN_SHADERS_GUID;
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Material properties feeding the glTF material slots, the first property present in the material is used.
+///
+/// Slots missing from the mapping file use the default property names, an empty list disables the slot.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct PropertyMapping {
+    pub base_color: Vec<String>,
+    pub base_color_texture: Vec<String>,
+    pub metallic: Vec<String>,
+    /// Metalness in R and smoothness in A channel.
+    pub metallic_texture: Vec<String>,
+    pub smoothness: Vec<String>,
+    /// Multiplier of the smoothness read from the texture.
+    pub smoothness_scale: Vec<String>,
+    /// 0 - smoothness in metallic texture alpha, 1 - in base color texture alpha.
+    pub smoothness_channel: Vec<String>,
+    pub normal_texture: Vec<String>,
+    pub normal_scale: Vec<String>,
+    pub occlusion_texture: Vec<String>,
+    pub occlusion_strength: Vec<String>,
+    pub emissive_color: Vec<String>,
+    pub emissive_texture: Vec<String>,
+    /// Shader keywords enabling the emission, without them emission is used when its color isn't black.
+    pub emission_keyword: Vec<String>,
+    /// 0 - Opaque, 1 - Transparent.
+    pub surface: Vec<String>,
+    /// 0 - Opaque, 1 - Cutout, 2 - Fade, 3 - Transparent.
+    pub mode: Vec<String>,
+    pub alpha_clip: Vec<String>,
+    pub alpha_cutoff: Vec<String>,
+    /// 0 - Off, 1 - Front, 2 - Back.
+    pub cull: Vec<String>,
+    pub double_sided: Vec<String>,
+}
+
+impl Default for PropertyMapping {
+    /// Property names of the Standard and URP/HDRP Lit shaders.
+    fn default() -> Self {
+        PropertyMapping {
+            base_color: names(&["_BaseColor", "_Color"]),
+            base_color_texture: names(&["_BaseMap", "_MainTex", "_BaseColorMap"]),
+            metallic: names(&["_Metallic"]),
+            metallic_texture: names(&["_MetallicGlossMap"]),
+            smoothness: names(&["_Smoothness", "_Glossiness"]),
+            // URP uses `_Smoothness` as the multiplier of the smoothness map, Standard `_GlossMapScale`
+            smoothness_scale: names(&["_Smoothness", "_GlossMapScale"]),
+            smoothness_channel: names(&["_SmoothnessTextureChannel"]),
+            normal_texture: names(&["_BumpMap", "_NormalMap"]),
+            normal_scale: names(&["_BumpScale", "_NormalScale"]),
+            occlusion_texture: names(&["_OcclusionMap"]),
+            occlusion_strength: names(&["_OcclusionStrength"]),
+            emissive_color: names(&["_EmissionColor", "_EmissiveColor"]),
+            emissive_texture: names(&["_EmissionMap", "_EmissiveColorMap"]),
+            emission_keyword: vec![],
+            surface: names(&["_Surface", "_SurfaceType"]),
+            mode: names(&["_Mode"]),
+            alpha_clip: names(&["_AlphaClip", "_AlphaCutoffEnable"]),
+            alpha_cutoff: names(&["_Cutoff", "_AlphaCutoff"]),
+            cull: names(&["_Cull", "_CullMode"]),
+            double_sided: names(&["_DoubleSidedEnable"]),
+        }
+    }
+}
+
+impl PropertyMapping {
+    /// Mapping without any properties.
+    pub fn empty() -> Self {
+        PropertyMapping {
+            base_color: vec![],
+            base_color_texture: vec![],
+            metallic: vec![],
+            metallic_texture: vec![],
+            smoothness: vec![],
+            smoothness_scale: vec![],
+            smoothness_channel: vec![],
+            normal_texture: vec![],
+            normal_scale: vec![],
+            occlusion_texture: vec![],
+            occlusion_strength: vec![],
+            emissive_color: vec![],
+            emissive_texture: vec![],
+            emission_keyword: vec![],
+            surface: vec![],
+            mode: vec![],
+            alpha_clip: vec![],
+            alpha_cutoff: vec![],
+            cull: vec![],
+            double_sided: vec![],
+        }
+    }
+}
+
+/// Mapping used for the materials of a single shader.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ShaderMapping {
+    /// GUID of the shader asset.
+    #[serde(default)]
+    pub guid: Option<String>,
+    /// File ID inside of the shader asset, needed for Unity built-in shaders sharing one GUID.
+    #[serde(default)]
+    pub file_id: Option<i64>,
+    /// Shader name, e.g. `Universal Render Pipeline/Lit`.
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub properties: PropertyMapping,
+}
+
+impl ShaderMapping {
+    pub fn matches(&self, shader: &FileReference, name: Option<&str>) -> bool {
+        let guid_matches = self.guid.is_some()
+            && self.guid == shader.guid
+            && self.file_id.is_none_or(|id| id == shader.file_id);
+        let name_matches = self.name.is_some() && self.name.as_deref() == name;
+        guid_matches || name_matches
+    }
+}
+
+/// Shader to glTF property mappings, user entries are checked before the built-in ones.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ShaderMappings {
+    #[serde(default, rename = "shader")]
+    pub shaders: Vec<ShaderMapping>,
+    /// Used for the materials of unmapped shaders.
+    #[serde(default)]
+    pub fallback: PropertyMapping,
+}
+
+impl Default for ShaderMappings {
+    fn default() -> Self {
+        ShaderMappings::builtin()
+    }
+}
+
+impl ShaderMappings {
+    /// Mappings for Standard, URP Lit/SimpleLit/Unlit and HDRP Lit shaders.
+    pub fn builtin() -> ShaderMappings {
+        let standard = PropertyMapping {
+            base_color: names(&["_Color"]),
+            base_color_texture: names(&["_MainTex"]),
+            smoothness: names(&["_Glossiness"]),
+            smoothness_scale: names(&["_GlossMapScale"]),
+            normal_texture: names(&["_BumpMap"]),
+            normal_scale: names(&["_BumpScale"]),
+            emissive_color: names(&["_EmissionColor"]),
+            emissive_texture: names(&["_EmissionMap"]),
+            emission_keyword: names(&["_EMISSION"]),
+            surface: vec![],
+            alpha_clip: vec![],
+            alpha_cutoff: names(&["_Cutoff"]),
+            cull: vec![],
+            double_sided: vec![],
+            ..Default::default()
+        };
+        let urp_lit = PropertyMapping {
+            base_color: names(&["_BaseColor"]),
+            base_color_texture: names(&["_BaseMap"]),
+            smoothness: names(&["_Smoothness"]),
+            smoothness_scale: names(&["_Smoothness"]),
+            normal_texture: names(&["_BumpMap"]),
+            normal_scale: names(&["_BumpScale"]),
+            emissive_color: names(&["_EmissionColor"]),
+            emissive_texture: names(&["_EmissionMap"]),
+            emission_keyword: names(&["_EMISSION"]),
+            surface: names(&["_Surface"]),
+            mode: vec![],
+            alpha_clip: names(&["_AlphaClip"]),
+            alpha_cutoff: names(&["_Cutoff"]),
+            cull: names(&["_Cull"]),
+            double_sided: vec![],
+            ..Default::default()
+        };
+        // specular workflow, only smoothness is used
+        let urp_simple_lit = PropertyMapping {
+            metallic: vec![],
+            metallic_texture: vec![],
+            smoothness_scale: vec![],
+            smoothness_channel: vec![],
+            occlusion_texture: vec![],
+            occlusion_strength: vec![],
+            ..urp_lit.clone()
+        };
+        let urp_unlit = PropertyMapping {
+            base_color: names(&["_BaseColor"]),
+            base_color_texture: names(&["_BaseMap"]),
+            surface: names(&["_Surface"]),
+            alpha_clip: names(&["_AlphaClip"]),
+            alpha_cutoff: names(&["_Cutoff"]),
+            cull: names(&["_Cull"]),
+            ..PropertyMapping::empty()
+        };
+        let hdrp_lit = PropertyMapping {
+            base_color: names(&["_BaseColor"]),
+            base_color_texture: names(&["_BaseColorMap"]),
+            // `_MaskMap` uses different channel layout than `_MetallicGlossMap`
+            metallic_texture: vec![],
+            smoothness: names(&["_Smoothness"]),
+            smoothness_scale: vec![],
+            smoothness_channel: vec![],
+            normal_texture: names(&["_NormalMap"]),
+            normal_scale: names(&["_NormalScale"]),
+            occlusion_texture: vec![],
+            occlusion_strength: vec![],
+            emissive_color: names(&["_EmissiveColor"]),
+            emissive_texture: names(&["_EmissiveColorMap"]),
+            surface: names(&["_SurfaceType"]),
+            mode: vec![],
+            alpha_clip: names(&["_AlphaCutoffEnable"]),
+            alpha_cutoff: names(&["_AlphaCutoff"]),
+            cull: vec![],
+            double_sided: names(&["_DoubleSidedEnable"]),
+            ..Default::default()
+        };
+        ShaderMappings {
+            shaders: vec![
+                builtin_mapping(BUILTIN_SHADERS_GUID, Some(46), "Standard", standard),
+                builtin_mapping(
+                    "933532a4fcc9baf4fa0491de14d08ed7",
+                    None,
+                    "Universal Render Pipeline/Lit",
+                    urp_lit,
+                ),
+                builtin_mapping(
+                    "8d2bb70cbf9db8d4da26e15b26e74248",
+                    None,
+                    "Universal Render Pipeline/Simple Lit",
+                    urp_simple_lit,
+                ),
+                builtin_mapping(
+                    "650dd9526735d5b46b79224bc6e94025",
+                    None,
+                    "Universal Render Pipeline/Unlit",
+                    urp_unlit,
+                ),
+                builtin_mapping(
+                    "6e4ae4064600d784cac1e41a9e6f2e59",
+                    None,
+                    "HDRP/Lit",
+                    hdrp_lit,
+                ),
+            ],
+            fallback: PropertyMapping::default(),
+        }
+    }
+
+    /// Reads TOML or JSON mapping file, built-in mappings are appended to the loaded ones.
+    pub fn load(path: &Path) -> Result<ShaderMappings> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let mut mappings: ShaderMappings = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => serde_json::from_str(&content)?,
+            _ => toml::from_str(&content)?,
+        };
+        mappings.shaders.extend(ShaderMappings::builtin().shaders);
+        Ok(mappings)
+    }
+
+    /// Mapping of the shader, `None` when there is no mapping for it.
+    pub fn find(&self, shader: &FileReference, name: Option<&str>) -> Option<&ShaderMapping> {
+        self.shaders.iter().find(|m| m.matches(shader, name))
+    }
+
+    /// Properties used for the shader, fallback ones for unmapped shaders.
+    pub fn properties(&self, shader: &FileReference, name: Option<&str>) -> &PropertyMapping {
+        self.find(shader, name)
+            .map(|m| &m.properties)
+            .unwrap_or(&self.fallback)
+    }
+}
+
+/// Name declared in the `.shader` file source, e.g. `Shader "Custom/Toon"`.
+pub fn shader_name(source: &str) -> Option<String> {
+    static SHADER_REGEX: OnceLock<Regex> = OnceLock::new();
+    let regex = SHADER_REGEX.get_or_init(|| Regex::new(r#"(?m)^\s*Shader\s+"([^"]+)""#).unwrap());
+    regex.captures(source).map(|c| c[1].to_string())
+}
+
+/// Name of the shader asset at `pathname`, read from the `.shader` source or built from the Shader
+/// Graph path and file name, as Unity does. `source` is `None` for assets that weren't extracted,
+/// the file name is used then. Other assets return `None`.
+pub fn package_shader_name(pathname: &str, source: Option<&str>) -> Option<String> {
+    static GRAPH_PATH_REGEX: OnceLock<Regex> = OnceLock::new();
+    let path = Path::new(pathname);
+    let stem = path.file_stem()?.to_str()?;
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("shader") => source.and_then(shader_name).or(Some(stem.to_string())),
+        Some("shadergraph") => {
+            let regex =
+                GRAPH_PATH_REGEX.get_or_init(|| Regex::new(r#""m_Path"\s*:\s*"([^"]*)""#).unwrap());
+            let graph_path = source
+                .and_then(|s| regex.captures(s))
+                .map(|c| c[1].to_string())
+                .unwrap_or("Shader Graphs".to_string());
+            match graph_path.is_empty() {
+                true => Some(stem.to_string()),
+                false => Some(format!("{}/{}", graph_path, stem)),
+            }
+        }
+        _ => None,
+    }
+}
+
+fn builtin_mapping(
+    guid: &str,
+    file_id: Option<i64>,
+    name: &str,
+    properties: PropertyMapping,
+) -> ShaderMapping {
+    ShaderMapping {
+        guid: Some(guid.to_string()),
+        file_id,
+        name: Some(name.to_string()),
+        properties,
+    }
+}
+
+fn names(names: &[&str]) -> Vec<String> {
+    names.iter().map(|n| n.to_string()).collect()
+}
@@ -0,0 +1,767 @@
+use crate::convert::glb::GlbFile;
+use crate::convert::mapping::{PropertyMapping, ShaderMappings};
+use crate::convert::texture::{MetallicSmoothness, SmoothnessChannel};
+use crate::primitives::materials::{SavedProperties, TextureInfo, UnityColor, UnityMaterial};
+use crate::primitives::prefab::{Component, Prefab};
+use crate::primitives::reference::FileReference;
+use crate::shader::ShaderInfo;
+use anyhow::Result;
+use gltf::json::{
+    self, extensions, material, material::AlphaMode, texture, validation::Checked, Index,
+};
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const TEXTURE_TRANSFORM_EXTENSION: &str = "KHR_texture_transform";
+const EMISSIVE_STRENGTH_EXTENSION: &str = "KHR_materials_emissive_strength";
+
+/// Everything needed to convert the materials of the package.
+#[derive(Clone, Debug, Default)]
+pub struct MaterialLibrary {
+    /// Parsed materials by GUID.
+    pub materials: HashMap<String, UnityMaterial>,
+    /// Unpacked asset paths by GUID.
+    pub asset_paths: HashMap<String, PathBuf>,
+    /// Names of the shaders included in the package by GUID.
+    pub shader_names: HashMap<String, String>,
+    pub mappings: ShaderMappings,
+}
+
+impl MaterialLibrary {
+    pub fn shader(&self, unity_material: &UnityMaterial) -> ShaderInfo {
+        ShaderInfo::resolve(&unity_material.shader, &self.shader_names)
+    }
+
+    pub fn properties(&self, unity_material: &UnityMaterial) -> &PropertyMapping {
+        let shader = self.shader(unity_material);
+        self.mappings
+            .properties(&unity_material.shader, shader.name.as_deref())
+    }
+
+    /// GUIDs of the materials using shaders without mapping, sorted.
+    pub fn unmapped(&self) -> Vec<&str> {
+        let mut unmapped: Vec<&str> = self
+            .materials
+            .iter()
+            .filter(|(_, m)| {
+                let shader = self.shader(m);
+                self.mappings
+                    .find(&m.shader, shader.name.as_deref())
+                    .is_none()
+            })
+            .map(|(guid, _)| guid.as_str())
+            .collect();
+        unmapped.sort_unstable();
+        unmapped
+    }
+}
+
+/// Translates Unity materials into glTF `pbrMetallicRoughness` ones.
+///
+/// Textures used by the material are copied next to the glb file and referenced by a relative uri.
+pub struct MaterialConverter<'a> {
+    glb_dir: PathBuf,
+    library: &'a MaterialLibrary,
+}
+
+impl<'a> MaterialConverter<'a> {
+    pub fn new(glb_path: &Path, library: &'a MaterialLibrary) -> Self {
+        MaterialConverter {
+            glb_dir: glb_path.parent().unwrap_or(Path::new("")).to_path_buf(),
+            library,
+        }
+    }
+
+    /// Builds glTF material, images and textures it needs are added to the `root`.
+    ///
+    /// `guid` is the GUID of the material, it keeps the names of generated textures unique.
+    pub fn convert(
+        &self,
+        root: &mut json::Root,
+        guid: &str,
+        unity_material: &UnityMaterial,
+    ) -> Result<json::Material> {
+        let properties = &unity_material.properties;
+        let mapping = self.library.properties(unity_material);
+        let base_color = color(properties, &mapping.base_color)
+            .map(|c| {
+                let [r, g, b] = srgb_to_linear(c);
+                [r, g, b, c.a]
+            })
+            .unwrap_or([1.0; 4]);
+        let smoothness = float(properties, &mapping.smoothness).unwrap_or(0.5);
+
+        let mut result = json::Material {
+            name: Some(unity_material.name.clone()),
+            pbr_metallic_roughness: material::PbrMetallicRoughness {
+                base_color_factor: material::PbrBaseColorFactor(base_color),
+                base_color_texture: self.texture_info(
+                    root,
+                    properties,
+                    &mapping.base_color_texture,
+                )?,
+                metallic_factor: material::StrengthFactor(
+                    float(properties, &mapping.metallic).unwrap_or_default(),
+                ),
+                roughness_factor: material::StrengthFactor(1.0 - smoothness.clamp(0.0, 1.0)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let (alpha_mode, alpha_cutoff) = alpha_mode(unity_material, mapping);
+        result.alpha_mode = Checked::Valid(alpha_mode);
+        result.alpha_cutoff = alpha_cutoff.map(material::AlphaCutoff);
+        result.double_sided = is_double_sided(properties, mapping);
+        if let Some(info) =
+            self.metallic_roughness_texture(root, guid, unity_material, mapping, smoothness)?
+        {
+            let pbr = &mut result.pbr_metallic_roughness;
+            pbr.metallic_roughness_texture = Some(info);
+            pbr.metallic_factor = material::StrengthFactor(1.0);
+            pbr.roughness_factor = material::StrengthFactor(1.0);
+        }
+        if let Some(info) = self.texture_info(root, properties, &mapping.normal_texture)? {
+            result.normal_texture = Some(material::NormalTexture {
+                index: info.index,
+                scale: float(properties, &mapping.normal_scale).unwrap_or(1.0),
+                tex_coord: info.tex_coord,
+                extensions: None,
+                extras: Default::default(),
+            });
+        }
+        if let Some(info) = self.texture_info(root, properties, &mapping.occlusion_texture)? {
+            result.occlusion_texture = Some(material::OcclusionTexture {
+                index: info.index,
+                strength: material::StrengthFactor(
+                    float(properties, &mapping.occlusion_strength).unwrap_or(1.0),
+                ),
+                tex_coord: info.tex_coord,
+                extensions: None,
+                extras: Default::default(),
+            });
+        }
+        if is_emission_enabled(unity_material, mapping) {
+            result.emissive_texture =
+                self.texture_info(root, properties, &mapping.emissive_texture)?;
+            let emissive_color = color(properties, &mapping.emissive_color).map(srgb_to_linear);
+            let (factor, strength) = match emissive_color {
+                Some(color) => split_intensity(color),
+                None if result.emissive_texture.is_some() => ([1.0; 3], None),
+                None => ([0.0; 3], None),
+            };
+            result.emissive_factor = material::EmissiveFactor(factor);
+            if let Some(strength) = strength {
+                let mut others = serde_json::Map::new();
+                others.insert(
+                    EMISSIVE_STRENGTH_EXTENSION.to_string(),
+                    json!({ "emissiveStrength": strength }),
+                );
+                result.extensions = Some(extensions::material::Material { others });
+                add_extension_used(root, EMISSIVE_STRENGTH_EXTENSION);
+            }
+        }
+        if let Some(transform) = texture_transform(properties, mapping) {
+            apply_texture_transform(&mut result, &transform);
+            add_extension_used(root, TEXTURE_TRANSFORM_EXTENSION);
+        }
+        Ok(result)
+    }
+
+    /// Packs Unity metallic and smoothness maps into glTF texture saved next to the glb file.
+    ///
+    /// Textures that can't be decoded are reported and the material falls back to the factors.
+    fn metallic_roughness_texture(
+        &self,
+        root: &mut json::Root,
+        guid: &str,
+        unity_material: &UnityMaterial,
+        mapping: &PropertyMapping,
+        smoothness: f32,
+    ) -> Result<Option<texture::Info>> {
+        let properties = &unity_material.properties;
+        let setup = MetallicSmoothness {
+            metallic_map: self.texture_path(properties, &mapping.metallic_texture),
+            albedo_map: self.texture_path(properties, &mapping.base_color_texture),
+            channel: float(properties, &mapping.smoothness_channel)
+                .map(SmoothnessChannel::from_property)
+                .unwrap_or_default(),
+            metallic: float(properties, &mapping.metallic).unwrap_or_default(),
+            smoothness,
+            smoothness_scale: float(properties, &mapping.smoothness_scale).unwrap_or(1.0),
+        };
+        let file_name = format!(
+            "{}_{}_metallicRoughness.png",
+            guid,
+            sanitize_file_name(&unity_material.name)
+        );
+        let target = self.glb_dir.join(&file_name);
+        if !target.exists() {
+            let image = match setup.to_metallic_roughness() {
+                Ok(Some(image)) => image,
+                Ok(None) => return Ok(None),
+                Err(e) => {
+                    eprintln!(
+                        "Failed to pack metallic roughness texture of {}: {:#}",
+                        unity_material.name, e
+                    );
+                    return Ok(None);
+                }
+            };
+            image.save(&target)?;
+        }
+        Ok(Some(texture::Info {
+            index: add_image_texture(root, &file_name),
+            tex_coord: 0,
+            extensions: None,
+            extras: Default::default(),
+        }))
+    }
+
+    fn texture_path(&self, properties: &SavedProperties, names: &[String]) -> Option<&Path> {
+        let info = names.iter().find_map(|name| properties.texture(name))?;
+        let guid = info.texture.guid.as_ref()?;
+        self.library.asset_paths.get(guid).map(PathBuf::as_path)
+    }
+
+    fn texture_info(
+        &self,
+        root: &mut json::Root,
+        properties: &SavedProperties,
+        names: &[String],
+    ) -> Result<Option<texture::Info>> {
+        let Some(info) = names.iter().find_map(|name| properties.texture(name)) else {
+            return Ok(None);
+        };
+        let Some(index) = self.add_texture(root, info)? else {
+            return Ok(None);
+        };
+        Ok(Some(texture::Info {
+            index,
+            tex_coord: 0,
+            extensions: None,
+            extras: Default::default(),
+        }))
+    }
+
+    /// Copies the texture next to the glb file and adds it to the `root`, reusing existing entries.
+    ///
+    /// Copies are prefixed with the texture GUID, so textures with the same name don't collide.
+    /// glTF allows only PNG and JPEG images, other formats (TGA, TIFF, etc.) are converted to PNG.
+    fn add_texture(
+        &self,
+        root: &mut json::Root,
+        info: &TextureInfo,
+    ) -> Result<Option<Index<json::Texture>>> {
+        let Some(guid) = info.texture.guid.as_ref() else {
+            return Ok(None);
+        };
+        let Some(source) = self.library.asset_paths.get(guid) else {
+            return Ok(None);
+        };
+        let Some(stem) = source.file_stem().and_then(|f| f.to_str()) else {
+            return Ok(None);
+        };
+        let extension = source
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase)
+            .unwrap_or_default();
+        let is_supported = matches!(extension.as_str(), "png" | "jpg" | "jpeg");
+        let file_name = if is_supported {
+            format!("{}_{}.{}", guid, stem, extension)
+        } else {
+            format!("{}_{}.png", guid, stem)
+        };
+        let target = self.glb_dir.join(&file_name);
+        if !target.exists() {
+            if is_supported {
+                fs::copy(source, &target)?;
+            } else {
+                let image = match image::open(source) {
+                    Ok(image) => image,
+                    Err(e) => {
+                        eprintln!("Failed to convert {} to PNG: {}", source.display(), e);
+                        return Ok(None);
+                    }
+                };
+                image.save(&target)?;
+            }
+        }
+        Ok(Some(add_image_texture(root, &file_name)))
+    }
+}
+
+/// Materials drawn on a single model mesh by a prefab renderer.
+#[derive(Debug, Clone)]
+pub struct MeshMaterials {
+    /// GUID of the model containing the mesh.
+    pub model_guid: String,
+    /// File ID of the mesh inside of the model.
+    pub mesh_file_id: i64,
+    /// Material GUIDs in submesh order, `None` for empty slots.
+    pub materials: Vec<Option<String>>,
+}
+
+impl MeshMaterials {
+    /// Pairs `MeshRenderer` with `MeshFilter` of the same GameObject, `SkinnedMeshRenderer` has its own mesh.
+    pub fn from_prefab(content: &str) -> Result<Vec<MeshMaterials>> {
+        let prefab = Prefab::parse(content);
+        let mut result = vec![];
+        for component in prefab.components.values() {
+            let (mesh, materials) = match component {
+                Component::MeshRenderer(renderer) => {
+                    let game_object = renderer.game_object.file_id;
+                    let mesh = prefab
+                        .components_of(game_object)
+                        .find_map(|(_, c)| match c {
+                            Component::MeshFilter(filter) => Some(&filter.mesh),
+                            _ => None,
+                        });
+                    (mesh, &renderer.materials)
+                }
+                Component::SkinnedMeshRenderer(renderer) => {
+                    (Some(&renderer.mesh), &renderer.materials)
+                }
+                _ => continue,
+            };
+            let Some(FileReference {
+                file_id: mesh_file_id,
+                guid: Some(model_guid),
+            }) = mesh
+            else {
+                continue;
+            };
+            result.push(MeshMaterials {
+                model_guid: model_guid.clone(),
+                mesh_file_id: *mesh_file_id,
+                materials: materials.iter().map(|m| m.guid.clone()).collect(),
+            });
+        }
+        Ok(result)
+    }
+}
+
+/// Replaces materials of the glb file with the converted Unity ones.
+///
+/// glTF materials are first replaced by name, using `remaps` from the model importer settings and
+/// names of the assigned Unity materials, then every primitive of the assigned meshes gets
+/// the material from its submesh slot.
+/// `mesh_names` maps mesh file IDs to names, as stored in the model importer settings.
+pub fn apply_materials(
+    glb_path: &Path,
+    meshes: &[MeshMaterials],
+    mesh_names: &HashMap<i64, String>,
+    remaps: &HashMap<String, String>,
+    library: &MaterialLibrary,
+) -> Result<()> {
+    let unity_materials = &library.materials;
+    let mut glb = GlbFile::read(glb_path)?;
+    let converter = MaterialConverter::new(glb_path, library);
+    let mut converted: HashMap<&str, Index<json::Material>> = HashMap::new();
+
+    let mut by_name: HashMap<&str, &str> = meshes
+        .iter()
+        .flat_map(|m| m.materials.iter().flatten())
+        .filter_map(|guid| {
+            let (guid, unity_material) = unity_materials.get_key_value(guid)?;
+            Some((unity_material.name.as_str(), guid.as_str()))
+        })
+        .collect();
+    by_name.extend(
+        remaps
+            .iter()
+            .filter(|(_, guid)| unity_materials.contains_key(*guid))
+            .map(|(name, guid)| (name.as_str(), guid.as_str())),
+    );
+    for i in 0..glb.json.materials.len() {
+        let Some(guid) = glb.json.materials[i]
+            .name
+            .as_deref()
+            .and_then(|name| by_name.get(name))
+        else {
+            continue;
+        };
+        glb.json.materials[i] = converter.convert(&mut glb.json, guid, &unity_materials[*guid])?;
+        converted.entry(guid).or_insert(Index::new(i as u32));
+    }
+
+    let mut slots = vec![];
+    for mesh_materials in meshes {
+        let name = mesh_names.get(&mesh_materials.mesh_file_id);
+        for mesh in find_meshes(&glb.json, name.map(String::as_str), meshes.len()) {
+            let primitives = glb.json.meshes[mesh].primitives.len();
+            for (primitive, guid) in mesh_materials.materials.iter().take(primitives).enumerate() {
+                if let Some(guid) = guid.as_deref().filter(|g| unity_materials.contains_key(*g)) {
+                    slots.push((mesh, primitive, guid));
+                }
+            }
+        }
+    }
+    for (mesh, primitive, guid) in slots {
+        let index = match converted.get(guid) {
+            Some(index) => *index,
+            None => {
+                let material = converter.convert(&mut glb.json, guid, &unity_materials[guid])?;
+                let index = glb.json.push(material);
+                converted.insert(guid, index);
+                index
+            }
+        };
+        glb.json.meshes[mesh].primitives[primitive].material = Some(index);
+    }
+    glb.write(glb_path)
+}
+
+/// Indices of glTF meshes with given name, mesh nodes are checked as well.
+///
+/// When the name is unknown the only mesh of the model is used for the only assignment.
+pub(crate) fn find_meshes(root: &json::Root, name: Option<&str>, assignments: usize) -> Vec<usize> {
+    let mut found: Vec<usize> = match name {
+        Some(name) => root
+            .meshes
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m.name.as_deref() == Some(name))
+            .map(|(i, _)| i)
+            .chain(
+                root.nodes
+                    .iter()
+                    .filter(|n| n.name.as_deref() == Some(name))
+                    .filter_map(|n| n.mesh.map(|m| m.value())),
+            )
+            .collect(),
+        None => vec![],
+    };
+    found.sort_unstable();
+    found.dedup();
+    if found.is_empty() && root.meshes.len() == 1 && assignments == 1 {
+        found.push(0);
+    }
+    found
+}
+
+/// Texture using an image with given uri, both are created when missing.
+fn add_image_texture(root: &mut json::Root, uri: &str) -> Index<json::Texture> {
+    let image = match root
+        .images
+        .iter()
+        .position(|i| i.uri.as_deref() == Some(uri))
+    {
+        Some(i) => Index::new(i as u32),
+        None => root.push(json::Image {
+            buffer_view: None,
+            mime_type: None,
+            name: Some(uri.to_string()),
+            uri: Some(uri.to_string()),
+            extensions: None,
+            extras: Default::default(),
+        }),
+    };
+    match root.textures.iter().position(|t| t.source == image) {
+        Some(i) => Index::new(i as u32),
+        None => root.push(json::Texture {
+            name: None,
+            sampler: None,
+            source: image,
+            extensions: None,
+            extras: Default::default(),
+        }),
+    }
+}
+
+/// `KHR_texture_transform` of the main texture tiling and offset, `None` for the default ones.
+///
+/// Standard and URP Lit shaders sample every map with the main texture `_ST` values.
+/// Unity UV origin is in the bottom left corner, so the vertical offset is flipped.
+fn texture_transform(properties: &SavedProperties, mapping: &PropertyMapping) -> Option<JsonValue> {
+    let info = mapping
+        .base_color_texture
+        .iter()
+        .find_map(|name| properties.tex_envs.iter().find_map(|tex| tex.get(name)))?;
+    let (scale, offset) = (info.scale, info.offset);
+    if (scale.x, scale.y, offset.x, offset.y) == (1.0, 1.0, 0.0, 0.0) {
+        return None;
+    }
+    Some(json!({
+        "offset": [offset.x, 1.0 - scale.y - offset.y],
+        "scale": [scale.x, scale.y],
+    }))
+}
+
+fn apply_texture_transform(material: &mut json::Material, transform: &JsonValue) {
+    let extension = || {
+        let mut others = serde_json::Map::new();
+        others.insert(TEXTURE_TRANSFORM_EXTENSION.to_string(), transform.clone());
+        others
+    };
+    let pbr = &mut material.pbr_metallic_roughness;
+    for info in [
+        &mut pbr.base_color_texture,
+        &mut pbr.metallic_roughness_texture,
+        &mut material.emissive_texture,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        info.extensions = Some(extensions::texture::Info {
+            others: extension(),
+        });
+    }
+    if let Some(normal) = &mut material.normal_texture {
+        normal.extensions = Some(extensions::material::NormalTexture {
+            others: extension(),
+        });
+    }
+    if let Some(occlusion) = &mut material.occlusion_texture {
+        occlusion.extensions = Some(extensions::material::OcclusionTexture {
+            others: extension(),
+        });
+    }
+}
+
+/// glTF alpha mode and cutoff based on the URP surface type, Standard mode or `RenderType` tag.
+pub fn alpha_mode(
+    unity_material: &UnityMaterial,
+    mapping: &PropertyMapping,
+) -> (AlphaMode, Option<f32>) {
+    let properties = &unity_material.properties;
+    let render_type = unity_material
+        .string_tags
+        .get("RenderType")
+        .map(String::as_str);
+    let mode = match (
+        float(properties, &mapping.surface),
+        float(properties, &mapping.mode),
+    ) {
+        // 0 - Opaque, 1 - Transparent
+        (Some(surface), _) if surface >= 0.5 => AlphaMode::Blend,
+        (Some(_), _) => AlphaMode::Opaque,
+        // 0 - Opaque, 1 - Cutout, 2 - Fade, 3 - Transparent
+        (None, Some(mode)) => match mode.round() as i32 {
+            1 => AlphaMode::Mask,
+            2 | 3 => AlphaMode::Blend,
+            _ => AlphaMode::Opaque,
+        },
+        (None, None) => match render_type {
+            Some("TransparentCutout") => AlphaMode::Mask,
+            Some("Transparent") => AlphaMode::Blend,
+            _ => AlphaMode::Opaque,
+        },
+    };
+    let alpha_clip = float(properties, &mapping.alpha_clip).is_some_and(|clip| clip >= 0.5);
+    let mode = match mode {
+        AlphaMode::Opaque if alpha_clip => AlphaMode::Mask,
+        mode => mode,
+    };
+    let cutoff = match mode {
+        AlphaMode::Mask => Some(float(properties, &mapping.alpha_cutoff).unwrap_or(0.5)),
+        _ => None,
+    };
+    (mode, cutoff)
+}
+
+/// Emission turned off in the material inspector only removes the shader keyword, the color stays.
+///
+/// Shaders without the keyword emit when the emission color is not black.
+fn is_emission_enabled(unity_material: &UnityMaterial, mapping: &PropertyMapping) -> bool {
+    if mapping.emission_keyword.is_empty() {
+        return color(&unity_material.properties, &mapping.emissive_color)
+            .is_some_and(|c| c.r > 0.0 || c.g > 0.0 || c.b > 0.0);
+    }
+    mapping
+        .emission_keyword
+        .iter()
+        .any(|keyword| unity_material.has_keyword(keyword))
+}
+
+/// Splits HDR color into glTF `emissiveFactor` and `KHR_materials_emissive_strength` multiplier.
+fn split_intensity(color: [f32; 3]) -> ([f32; 3], Option<f32>) {
+    let intensity = color.into_iter().fold(0.0, f32::max);
+    if intensity <= 1.0 {
+        return (color.map(|c| c.max(0.0)), None);
+    }
+    (color.map(|c| (c / intensity).max(0.0)), Some(intensity))
+}
+
+fn add_extension_used(root: &mut json::Root, name: &str) {
+    if !root.extensions_used.iter().any(|used| used == name) {
+        root.extensions_used.push(name.to_string());
+    }
+}
+
+/// Culling set to `Off` or HDRP double sided flag.
+fn is_double_sided(properties: &SavedProperties, mapping: &PropertyMapping) -> bool {
+    // 0 - Off, 1 - Front, 2 - Back
+    float(properties, &mapping.cull).is_some_and(|cull| cull < 0.5)
+        || float(properties, &mapping.double_sided).is_some_and(|enabled| enabled >= 0.5)
+}
+
+/// Material names can contain characters not allowed in file names.
+fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+fn float(properties: &SavedProperties, names: &[String]) -> Option<f32> {
+    names.iter().find_map(|name| properties.float(name))
+}
+
+fn color(properties: &SavedProperties, names: &[String]) -> Option<UnityColor> {
+    names.iter().find_map(|name| properties.color(name))
+}
+
+/// Unity serializes material colors in gamma space, glTF factors are linear.
+pub fn srgb_to_linear(color: UnityColor) -> [f32; 3] {
+    [color.r, color.g, color.b].map(|c| {
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::materials::UnityVector2;
+
+    fn floats(values: &[(&str, f32)]) -> SavedProperties {
+        SavedProperties {
+            floats: values
+                .iter()
+                .map(|(name, value)| HashMap::from([(name.to_string(), *value)]))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn material(values: &[(&str, f32)], render_type: Option<&str>) -> UnityMaterial {
+        UnityMaterial {
+            properties: floats(values),
+            string_tags: render_type
+                .map(|tag| HashMap::from([("RenderType".to_string(), tag.to_string())]))
+                .unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn urp_surface_and_alpha_clip() {
+        let mapping = PropertyMapping::default();
+        let transparent = material(&[("_Surface", 1.0), ("_AlphaClip", 1.0)], None);
+        assert_eq!(alpha_mode(&transparent, &mapping), (AlphaMode::Blend, None));
+        let clipped = material(
+            &[("_Surface", 0.0), ("_AlphaClip", 1.0), ("_Cutoff", 0.3)],
+            None,
+        );
+        assert_eq!(alpha_mode(&clipped, &mapping), (AlphaMode::Mask, Some(0.3)));
+        // surface wins over the RenderType tag
+        let opaque = material(&[("_Surface", 0.0)], Some("Transparent"));
+        assert_eq!(alpha_mode(&opaque, &mapping), (AlphaMode::Opaque, None));
+    }
+
+    #[test]
+    fn standard_mode() {
+        let mapping = PropertyMapping::default();
+        let cases = [
+            (0.0, AlphaMode::Opaque, None),
+            (1.0, AlphaMode::Mask, Some(0.5)),
+            (2.0, AlphaMode::Blend, None),
+            (3.0, AlphaMode::Blend, None),
+        ];
+        for (mode, expected, cutoff) in cases {
+            let material = material(&[("_Mode", mode)], None);
+            assert_eq!(alpha_mode(&material, &mapping), (expected, cutoff));
+        }
+    }
+
+    #[test]
+    fn render_type_tag() {
+        let mapping = PropertyMapping::default();
+        let cutout = material(&[], Some("TransparentCutout"));
+        assert_eq!(alpha_mode(&cutout, &mapping), (AlphaMode::Mask, Some(0.5)));
+        let transparent = material(&[], Some("Transparent"));
+        assert_eq!(alpha_mode(&transparent, &mapping), (AlphaMode::Blend, None));
+        let opaque = material(&[], None);
+        assert_eq!(alpha_mode(&opaque, &mapping), (AlphaMode::Opaque, None));
+    }
+
+    #[test]
+    fn double_sided() {
+        let mapping = PropertyMapping::default();
+        assert!(is_double_sided(&floats(&[("_Cull", 0.0)]), &mapping));
+        assert!(!is_double_sided(&floats(&[("_Cull", 2.0)]), &mapping));
+        assert!(is_double_sided(
+            &floats(&[("_DoubleSidedEnable", 1.0)]),
+            &mapping
+        ));
+        assert!(!is_double_sided(&floats(&[]), &mapping));
+    }
+
+    fn main_texture(scale: (f32, f32), offset: (f32, f32)) -> SavedProperties {
+        let info = TextureInfo {
+            scale: UnityVector2 {
+                x: scale.0,
+                y: scale.1,
+            },
+            offset: UnityVector2 {
+                x: offset.0,
+                y: offset.1,
+            },
+            ..Default::default()
+        };
+        SavedProperties {
+            tex_envs: vec![HashMap::from([("_MainTex".to_string(), info)])],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn default_tiling_has_no_transform() {
+        let properties = main_texture((1.0, 1.0), (0.0, 0.0));
+        assert_eq!(
+            texture_transform(&properties, &PropertyMapping::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn vertical_offset_is_flipped() {
+        let mapping = PropertyMapping::default();
+        let tiled = main_texture((2.0, 2.0), (0.0, 0.0));
+        assert_eq!(
+            texture_transform(&tiled, &mapping),
+            Some(json!({ "offset": [0.0, -1.0], "scale": [2.0, 2.0] }))
+        );
+        let shifted = main_texture((1.0, 0.5), (0.25, 0.25));
+        assert_eq!(
+            texture_transform(&shifted, &mapping),
+            Some(json!({ "offset": [0.25, 0.25], "scale": [1.0, 0.5] }))
+        );
+    }
+
+    #[test]
+    fn ldr_emission_has_no_strength() {
+        assert_eq!(split_intensity([0.5, 1.0, 0.0]), ([0.5, 1.0, 0.0], None));
+        assert_eq!(split_intensity([-0.1, 0.2, 0.3]), ([0.0, 0.2, 0.3], None));
+    }
+
+    #[test]
+    fn hdr_emission_is_normalized() {
+        assert_eq!(
+            split_intensity([4.0, 2.0, 1.0]),
+            ([1.0, 0.5, 0.25], Some(4.0))
+        );
+        assert_eq!(
+            split_intensity([2.0, -1.0, 0.0]),
+            ([1.0, 0.0, 0.0], Some(2.0))
+        );
+    }
+}
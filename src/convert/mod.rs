@@ -0,0 +1,7 @@
+//! Conversion of the unpacked Unity assets into glTF data.
+
+pub mod glb;
+pub mod mapping;
+pub mod material;
+pub mod scene;
+pub mod texture;
@@ -0,0 +1,666 @@
+use crate::convert::glb::GlbFile;
+use crate::convert::material::{find_meshes, MaterialConverter, MaterialLibrary};
+use crate::primitives::prefab::{
+    Component, Prefab, PrefabInstance, PrefabNode, PropertyModification, Transform, UnityVector3,
+};
+use anyhow::{bail, Result};
+use gltf::json::{self, buffer, mesh, scene, Index};
+use serde_yaml::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Nested prefabs deeper than that are skipped, it also stops prefabs nesting themselves.
+const MAX_NESTING_DEPTH: usize = 16;
+
+/// Model converted to glb, used as the mesh source of the prefabs.
+#[derive(Clone, Debug, Default)]
+pub struct ModelSource {
+    pub glb_path: PathBuf,
+    /// Mesh and node names by file ID, as stored in the model importer settings.
+    pub object_names: HashMap<i64, String>,
+    /// Project material GUIDs by embedded material name.
+    pub remaps: HashMap<String, String>,
+}
+
+/// Builds one glb per prefab with nodes following the Transform hierarchy.
+///
+/// Meshes are copied from the converted models and get the materials of the prefab renderers.
+/// Nested prefabs and models are included with their root placement, other overrides are ignored.
+pub struct PrefabExporter<'a> {
+    library: &'a MaterialLibrary,
+    /// Converted models by GUID.
+    models: &'a HashMap<String, ModelSource>,
+    /// Unpacked prefab paths by GUID, used for the nested prefabs.
+    prefabs: &'a HashMap<String, PathBuf>,
+}
+
+impl<'a> PrefabExporter<'a> {
+    pub fn new(
+        library: &'a MaterialLibrary,
+        models: &'a HashMap<String, ModelSource>,
+        prefabs: &'a HashMap<String, PathBuf>,
+    ) -> Self {
+        PrefabExporter {
+            library,
+            models,
+            prefabs,
+        }
+    }
+
+    /// Writes the prefab as glb, textures are copied next to it.
+    pub fn export(&self, prefab_path: &Path, glb_path: &Path) -> Result<()> {
+        let prefab = Prefab::parse(&fs::read_to_string(prefab_path)?);
+        let mut builder = SceneBuilder {
+            exporter: self,
+            converter: MaterialConverter::new(glb_path, self.library),
+            root: json::Root::default(),
+            blob: vec![],
+            glbs: HashMap::new(),
+            views: HashMap::new(),
+            accessors: HashMap::new(),
+            meshes: HashMap::new(),
+            materials: HashMap::new(),
+            depth: 0,
+        };
+        let nodes = builder.prefab_nodes(&prefab, None, &mut HashMap::new())?;
+        builder.finish(nodes).write(glb_path)
+    }
+}
+
+type MeshKey = (String, usize, Vec<Option<String>>);
+
+struct SceneBuilder<'a> {
+    exporter: &'a PrefabExporter<'a>,
+    converter: MaterialConverter<'a>,
+    root: json::Root,
+    blob: Vec<u8>,
+    /// Loaded models by GUID, `None` for the ones that failed to load.
+    glbs: HashMap<String, Option<Rc<GlbFile>>>,
+    views: HashMap<(String, usize), Index<buffer::View>>,
+    accessors: HashMap<(String, usize), Index<json::Accessor>>,
+    meshes: HashMap<MeshKey, Index<json::Mesh>>,
+    materials: HashMap<String, Index<json::Material>>,
+    depth: usize,
+}
+
+impl SceneBuilder<'_> {
+    fn finish(mut self, nodes: Vec<Index<json::Node>>) -> GlbFile {
+        self.root.push(json::Scene {
+            extensions: None,
+            extras: Default::default(),
+            name: None,
+            nodes,
+        });
+        self.root.scene = Some(Index::new(0));
+        if self.blob.is_empty() {
+            return GlbFile {
+                json: self.root,
+                blob: None,
+            };
+        }
+        pad_to_four(&mut self.blob);
+        self.root.push(json::Buffer {
+            byte_length: self.blob.len().into(),
+            name: None,
+            uri: None,
+            extensions: None,
+            extras: Default::default(),
+        });
+        GlbFile {
+            json: self.root,
+            blob: Some(self.blob),
+        }
+    }
+
+    /// Root nodes of the prefab, `instance` overrides the roots of the nested prefab.
+    ///
+    /// Nodes built for the transforms are collected in `sources` by the transform file ID.
+    fn prefab_nodes(
+        &mut self,
+        prefab: &Prefab,
+        instance: Option<&PrefabInstance>,
+        sources: &mut HashMap<i64, Index<json::Node>>,
+    ) -> Result<Vec<Index<json::Node>>> {
+        let mut nodes = vec![];
+        for node in prefab.tree() {
+            nodes.extend(self.node(prefab, &node, instance, sources)?);
+        }
+        Ok(nodes)
+    }
+
+    fn node(
+        &mut self,
+        prefab: &Prefab,
+        node: &PrefabNode,
+        instance: Option<&PrefabInstance>,
+        sources: &mut HashMap<i64, Index<json::Node>>,
+    ) -> Result<Option<Index<json::Node>>> {
+        let (mut gltf_node, nested_sources) = match node.transform {
+            Some(transform) => {
+                let Some(game_object) = prefab.game_objects.get(&node.file_id) else {
+                    return Ok(None);
+                };
+                let overrides: Vec<&PropertyModification> = instance
+                    .into_iter()
+                    .flat_map(|i| {
+                        i.modifications_of(transform)
+                            .chain(i.modifications_of(node.file_id))
+                    })
+                    .collect();
+                if !is_active(game_object.is_active, &overrides) {
+                    return Ok(None);
+                }
+                let transform = prefab
+                    .components
+                    .get(&transform)
+                    .and_then(Component::as_transform)
+                    .cloned()
+                    .unwrap_or_default();
+                let mut gltf_node = new_node(&transform, &overrides);
+                if gltf_node.name.is_none() {
+                    gltf_node.name = Some(game_object.name.clone());
+                }
+                gltf_node.mesh = self.renderer_mesh(prefab, node.file_id)?;
+                (gltf_node, HashMap::new())
+            }
+            None => self.instance_node(prefab, node.file_id)?,
+        };
+        let mut children = gltf_node.children.take().unwrap_or_default();
+        for child in &node.children {
+            let Some(index) = self.node(prefab, child, None, sources)? else {
+                continue;
+            };
+            // objects added under the nested prefab objects keep their parent
+            match child.source_parent.and_then(|s| nested_sources.get(&s)) {
+                Some(parent) => self.root.nodes[parent.value()]
+                    .children
+                    .get_or_insert_with(Vec::new)
+                    .push(index),
+                None => children.push(index),
+            }
+        }
+        gltf_node.children = (!children.is_empty()).then_some(children);
+        let index = self.root.push(gltf_node);
+        if let Some(transform) = node.transform {
+            sources.insert(transform, index);
+        }
+        Ok(Some(index))
+    }
+
+    /// Nested prefab or model, its content becomes the node children.
+    ///
+    /// Models keep the root placement on the instance node, nested prefabs apply it to their roots.
+    /// Returns the node with the nodes of the source objects by file ID.
+    fn instance_node(
+        &mut self,
+        prefab: &Prefab,
+        instance_id: i64,
+    ) -> Result<(json::Node, HashMap<i64, Index<json::Node>>)> {
+        let instance = &prefab.prefab_instances[&instance_id];
+        let mut gltf_node = json::Node {
+            name: instance.name().map(str::to_string),
+            ..Default::default()
+        };
+        let mut sources = HashMap::new();
+        let Some(guid) = instance.source_prefab.guid.as_deref() else {
+            return Ok((gltf_node, sources));
+        };
+        if self.depth >= MAX_NESTING_DEPTH {
+            eprintln!("Nested prefab {} skipped, too deep nesting", guid);
+            return Ok((gltf_node, sources));
+        }
+        let children = if let Some(model) = self.exporter.models.get(guid) {
+            let overrides: Vec<&PropertyModification> = prefab
+                .instance_root_source(instance_id)
+                .map(|source| instance.modifications_of(source).collect())
+                .unwrap_or_default();
+            gltf_node = json::Node {
+                name: gltf_node.name,
+                ..new_node(&Transform::default(), &overrides)
+            };
+            let first = self.root.nodes.len();
+            let nodes = self.model_nodes(guid)?;
+            // model nodes are named after the model objects
+            for (file_id, name) in &model.object_names {
+                let nodes = &self.root.nodes[first..];
+                if let Some(i) = nodes.iter().position(|n| n.name.as_ref() == Some(name)) {
+                    sources.insert(*file_id, Index::new((first + i) as u32));
+                }
+            }
+            nodes
+        } else if let Some(path) = self.exporter.prefabs.get(guid) {
+            let nested = Prefab::parse(&fs::read_to_string(path)?);
+            self.depth += 1;
+            let nodes = self.prefab_nodes(&nested, Some(instance), &mut sources);
+            self.depth -= 1;
+            nodes?
+        } else {
+            vec![]
+        };
+        gltf_node.children = (!children.is_empty()).then_some(children);
+        Ok((gltf_node, sources))
+    }
+
+    /// Mesh of the enabled `MeshRenderer` and `MeshFilter` or `SkinnedMeshRenderer` of the GameObject.
+    ///
+    /// Skinned meshes are exported in their bind pose.
+    fn renderer_mesh(
+        &mut self,
+        prefab: &Prefab,
+        game_object: i64,
+    ) -> Result<Option<Index<json::Mesh>>> {
+        let mut filter_mesh = None;
+        let mut renderer = None;
+        for (_, component) in prefab.components_of(game_object) {
+            match component {
+                Component::MeshFilter(filter) => filter_mesh = Some(&filter.mesh),
+                Component::MeshRenderer(r) if r.enabled => renderer = Some((None, &r.materials)),
+                Component::SkinnedMeshRenderer(r) if r.enabled => {
+                    renderer = Some((Some(&r.mesh), &r.materials))
+                }
+                _ => {}
+            }
+        }
+        let Some((skinned_mesh, materials)) = renderer else {
+            return Ok(None);
+        };
+        let Some(mesh) = skinned_mesh.or(filter_mesh) else {
+            return Ok(None);
+        };
+        let Some(model_guid) = mesh.guid.as_deref() else {
+            return Ok(None);
+        };
+        let Some(model) = self.exporter.models.get(model_guid) else {
+            return Ok(None);
+        };
+        let Some(glb) = self.glb(model_guid) else {
+            return Ok(None);
+        };
+        let name = model.object_names.get(&mesh.file_id).map(String::as_str);
+        let Some(&source_mesh) = find_meshes(&glb.json, name, 1).first() else {
+            return Ok(None);
+        };
+        let materials: Vec<Option<String>> = materials.iter().map(|m| m.guid.clone()).collect();
+        self.mesh(model_guid, &glb, source_mesh, &materials)
+            .map(Some)
+    }
+
+    /// Nodes of the model default scene, with the model materials.
+    fn model_nodes(&mut self, guid: &str) -> Result<Vec<Index<json::Node>>> {
+        let Some(glb) = self.glb(guid) else {
+            return Ok(vec![]);
+        };
+        let scene = glb.json.scene.map(|s| s.value()).unwrap_or_default();
+        let Some(scene) = glb.json.scenes.get(scene) else {
+            return Ok(vec![]);
+        };
+        scene
+            .nodes
+            .iter()
+            .map(|node| self.model_node(guid, &glb, node.value()))
+            .collect()
+    }
+
+    fn model_node(&mut self, guid: &str, glb: &GlbFile, node: usize) -> Result<Index<json::Node>> {
+        let source = &glb.json.nodes[node];
+        let children = source
+            .children
+            .iter()
+            .flatten()
+            .map(|child| self.model_node(guid, glb, child.value()))
+            .collect::<Result<Vec<_>>>()?;
+        let mesh = source
+            .mesh
+            .map(|mesh| self.mesh(guid, glb, mesh.value(), &[]))
+            .transpose()?;
+        Ok(self.root.push(json::Node {
+            children: (!children.is_empty()).then_some(children),
+            mesh,
+            camera: None,
+            skin: None,
+            weights: None,
+            extensions: None,
+            extras: Default::default(),
+            ..source.clone()
+        }))
+    }
+
+    fn glb(&mut self, guid: &str) -> Option<Rc<GlbFile>> {
+        if let Some(glb) = self.glbs.get(guid) {
+            return glb.clone();
+        }
+        let model = &self.exporter.models[guid];
+        let glb = match GlbFile::read(&model.glb_path) {
+            Ok(glb) => Some(Rc::new(glb)),
+            Err(e) => {
+                eprintln!("Failed to read {}: {:#}", model.glb_path.display(), e);
+                None
+            }
+        };
+        self.glbs.insert(guid.to_string(), glb.clone());
+        glb
+    }
+
+    /// Copy of the model mesh, primitives get the materials from the submesh slots.
+    ///
+    /// Primitives without a slot use the Unity material the model material is remapped to,
+    /// or the one with the same name.
+    fn mesh(
+        &mut self,
+        guid: &str,
+        glb: &GlbFile,
+        mesh: usize,
+        materials: &[Option<String>],
+    ) -> Result<Index<json::Mesh>> {
+        let key = (guid.to_string(), mesh, materials.to_vec());
+        if let Some(index) = self.meshes.get(&key) {
+            return Ok(*index);
+        }
+        let source = &glb.json.meshes[mesh];
+        let mut primitives = vec![];
+        for (i, primitive) in source.primitives.iter().enumerate() {
+            let mut attributes = BTreeMap::new();
+            for (semantic, accessor) in &primitive.attributes {
+                attributes.insert(semantic.clone(), self.accessor(guid, glb, *accessor)?);
+            }
+            let indices = primitive
+                .indices
+                .map(|a| self.accessor(guid, glb, a))
+                .transpose()?;
+            let targets = match &primitive.targets {
+                Some(targets) => Some(
+                    targets
+                        .iter()
+                        .map(|target| self.morph_target(guid, glb, target))
+                        .collect::<Result<Vec<_>>>()?,
+                ),
+                None => None,
+            };
+            let material_guid = materials
+                .get(i)
+                .cloned()
+                .flatten()
+                .filter(|g| self.exporter.library.materials.contains_key(g))
+                .or_else(|| self.remapped_material(guid, glb, primitive.material));
+            let material = material_guid
+                .map(|material| self.material(&material))
+                .transpose()?;
+            primitives.push(json::mesh::Primitive {
+                attributes,
+                indices,
+                material,
+                mode: primitive.mode,
+                targets,
+                extensions: None,
+                extras: Default::default(),
+            });
+        }
+        let index = self.root.push(json::Mesh {
+            name: source.name.clone(),
+            primitives,
+            weights: source.weights.clone(),
+            extensions: None,
+            extras: Default::default(),
+        });
+        self.meshes.insert(key, index);
+        Ok(index)
+    }
+
+    fn remapped_material(
+        &self,
+        guid: &str,
+        glb: &GlbFile,
+        material: Option<Index<json::Material>>,
+    ) -> Option<String> {
+        let name = glb.json.materials.get(material?.value())?.name.as_deref()?;
+        let materials = &self.exporter.library.materials;
+        self.exporter.models[guid]
+            .remaps
+            .get(name)
+            .filter(|g| materials.contains_key(*g))
+            .cloned()
+            .or_else(|| {
+                materials
+                    .iter()
+                    .find(|(_, m)| m.name == name)
+                    .map(|(g, _)| g.clone())
+            })
+    }
+
+    fn material(&mut self, guid: &str) -> Result<Index<json::Material>> {
+        if let Some(index) = self.materials.get(guid) {
+            return Ok(*index);
+        }
+        let unity_material = &self.exporter.library.materials[guid];
+        let material = self
+            .converter
+            .convert(&mut self.root, guid, unity_material)?;
+        let index = self.root.push(material);
+        self.materials.insert(guid.to_string(), index);
+        Ok(index)
+    }
+
+    fn morph_target(
+        &mut self,
+        guid: &str,
+        glb: &GlbFile,
+        target: &mesh::MorphTarget,
+    ) -> Result<mesh::MorphTarget> {
+        let mut copy = |accessor: Option<Index<json::Accessor>>| {
+            accessor.map(|a| self.accessor(guid, glb, a)).transpose()
+        };
+        Ok(mesh::MorphTarget {
+            positions: copy(target.positions)?,
+            normals: copy(target.normals)?,
+            tangents: copy(target.tangents)?,
+        })
+    }
+
+    fn accessor(
+        &mut self,
+        guid: &str,
+        glb: &GlbFile,
+        accessor: Index<json::Accessor>,
+    ) -> Result<Index<json::Accessor>> {
+        let key = (guid.to_string(), accessor.value());
+        if let Some(index) = self.accessors.get(&key) {
+            return Ok(*index);
+        }
+        let source = &glb.json.accessors[accessor.value()];
+        if source.sparse.is_some() {
+            bail!("sparse accessors are not supported");
+        }
+        let buffer_view = source
+            .buffer_view
+            .map(|view| self.view(guid, glb, view))
+            .transpose()?;
+        let index = self.root.push(json::Accessor {
+            buffer_view,
+            ..source.clone()
+        });
+        self.accessors.insert(key, index);
+        Ok(index)
+    }
+
+    fn view(
+        &mut self,
+        guid: &str,
+        glb: &GlbFile,
+        view: Index<buffer::View>,
+    ) -> Result<Index<buffer::View>> {
+        let key = (guid.to_string(), view.value());
+        if let Some(index) = self.views.get(&key) {
+            return Ok(*index);
+        }
+        let source = &glb.json.buffer_views[view.value()];
+        let (Some(blob), 0) = (&glb.blob, source.buffer.value()) else {
+            bail!("only data of the binary chunk is supported");
+        };
+        let offset = source.byte_offset.map(|o| o.0 as usize).unwrap_or_default();
+        let length = source.byte_length.0 as usize;
+        let Some(data) = blob.get(offset..offset + length) else {
+            bail!("buffer view out of the binary chunk");
+        };
+        pad_to_four(&mut self.blob);
+        let byte_offset = self.blob.len();
+        self.blob.extend_from_slice(data);
+        let index = self.root.push(buffer::View {
+            buffer: Index::new(0),
+            byte_offset: Some(byte_offset.into()),
+            ..source.clone()
+        });
+        self.views.insert(key, index);
+        Ok(index)
+    }
+}
+
+/// Node with the Unity transform converted into glTF right-handed space, X axis is flipped.
+///
+/// Modifications of the nested prefab root take precedence over the `transform` values.
+fn new_node(transform: &Transform, overrides: &[&PropertyModification]) -> json::Node {
+    let mut position = transform.local_position;
+    let mut rotation = transform.local_rotation;
+    let mut scale = transform.local_scale;
+    let mut name = None;
+    for modification in overrides {
+        let path = modification.property_path.as_str();
+        if path == "m_Name" {
+            name = modification.value.as_str().map(str::to_string);
+            continue;
+        }
+        let Some(value) = number(&modification.value) else {
+            continue;
+        };
+        match path {
+            "m_LocalPosition.x" => position.x = value,
+            "m_LocalPosition.y" => position.y = value,
+            "m_LocalPosition.z" => position.z = value,
+            "m_LocalRotation.x" => rotation.x = value,
+            "m_LocalRotation.y" => rotation.y = value,
+            "m_LocalRotation.z" => rotation.z = value,
+            "m_LocalRotation.w" => rotation.w = value,
+            "m_LocalScale.x" => scale.x = value,
+            "m_LocalScale.y" => scale.y = value,
+            "m_LocalScale.z" => scale.z = value,
+            _ => {}
+        }
+    }
+    let length = (rotation.x * rotation.x
+        + rotation.y * rotation.y
+        + rotation.z * rotation.z
+        + rotation.w * rotation.w)
+        .sqrt();
+    let rotation = match length {
+        length if length > 0.0 => [
+            rotation.x / length,
+            -rotation.y / length,
+            -rotation.z / length,
+            rotation.w / length,
+        ],
+        _ => [0.0, 0.0, 0.0, 1.0],
+    };
+    json::Node {
+        name,
+        translation: (position != UnityVector3::default()).then_some([
+            -position.x,
+            position.y,
+            position.z,
+        ]),
+        rotation: (rotation != [0.0, 0.0, 0.0, 1.0]).then_some(scene::UnitQuaternion(rotation)),
+        scale: (scale != UNIT_SCALE).then_some([scale.x, scale.y, scale.z]),
+        ..Default::default()
+    }
+}
+
+const UNIT_SCALE: UnityVector3 = UnityVector3 {
+    x: 1.0,
+    y: 1.0,
+    z: 1.0,
+};
+
+fn is_active(is_active: bool, overrides: &[&PropertyModification]) -> bool {
+    overrides
+        .iter()
+        .find(|m| m.property_path == "m_IsActive")
+        .and_then(|m| number(&m.value))
+        .map(|value| value != 0.0)
+        .unwrap_or(is_active)
+}
+
+/// Modification values are written as numbers or strings.
+fn number(value: &Value) -> Option<f32> {
+    match value {
+        Value::Number(number) => number.as_f64().map(|n| n as f32),
+        Value::String(text) => text.parse().ok(),
+        _ => None,
+    }
+}
+
+fn pad_to_four(blob: &mut Vec<u8>) {
+    blob.resize((blob.len() + 3) & !3, 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::prefab::UnityQuaternion;
+
+    fn modification(property_path: &str, value: f32) -> PropertyModification {
+        PropertyModification {
+            target: Default::default(),
+            property_path: property_path.to_string(),
+            value: Value::from(value),
+            object_reference: Default::default(),
+        }
+    }
+
+    #[test]
+    fn flips_x_axis() {
+        let transform = Transform {
+            local_position: UnityVector3 {
+                x: 1.0,
+                y: 2.0,
+                z: 3.0,
+            },
+            local_rotation: UnityQuaternion {
+                x: 0.5,
+                y: 0.5,
+                z: 0.5,
+                w: 0.5,
+            },
+            local_scale: UnityVector3 {
+                x: 2.0,
+                y: 3.0,
+                z: 4.0,
+            },
+            ..Default::default()
+        };
+        let node = new_node(&transform, &[]);
+        assert_eq!(node.translation, Some([-1.0, 2.0, 3.0]));
+        assert_eq!(node.rotation.map(|r| r.0), Some([0.5, -0.5, -0.5, 0.5]));
+        assert_eq!(node.scale, Some([2.0, 3.0, 4.0]));
+    }
+
+    #[test]
+    fn identity_has_no_transform() {
+        let node = new_node(&Transform::default(), &[]);
+        assert_eq!(node.translation, None);
+        assert!(node.rotation.is_none());
+        assert_eq!(node.scale, None);
+    }
+
+    #[test]
+    fn overrides_are_converted() {
+        let overrides = [
+            modification("m_LocalPosition.x", 4.0),
+            modification("m_LocalRotation.y", 1.0),
+            modification("m_LocalRotation.w", 0.0),
+        ];
+        let overrides: Vec<&PropertyModification> = overrides.iter().collect();
+        let node = new_node(&Transform::default(), &overrides);
+        assert_eq!(node.translation, Some([-4.0, 0.0, 0.0]));
+        assert_eq!(node.rotation.map(|r| r.0), Some([0.0, -1.0, 0.0, 0.0]));
+    }
+}
@@ -0,0 +1,182 @@
+use anyhow::{Context, Result};
+use image::{imageops, RgbImage, RgbaImage};
+use std::path::Path;
+
+/// Texture Unity reads the smoothness from, `_SmoothnessTextureChannel` property.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum SmoothnessChannel {
+    /// Alpha channel of the `_MetallicGlossMap`.
+    #[default]
+    MetallicAlpha,
+    /// Alpha channel of the albedo texture.
+    AlbedoAlpha,
+}
+
+impl SmoothnessChannel {
+    pub fn from_property(value: f32) -> SmoothnessChannel {
+        if value >= 0.5 {
+            SmoothnessChannel::AlbedoAlpha
+        } else {
+            SmoothnessChannel::MetallicAlpha
+        }
+    }
+}
+
+/// Metallic and smoothness setup of the Unity Standard or URP Lit material.
+#[derive(Clone, Debug, Default)]
+pub struct MetallicSmoothness<'a> {
+    /// Metalness in R and, by default, smoothness in A channel.
+    pub metallic_map: Option<&'a Path>,
+    pub albedo_map: Option<&'a Path>,
+    pub channel: SmoothnessChannel,
+    /// Metalness used when there is no metallic map.
+    pub metallic: f32,
+    /// Smoothness used when the albedo alpha is selected and there is no albedo map.
+    pub smoothness: f32,
+    /// Multiplier of the smoothness read from the texture.
+    pub smoothness_scale: f32,
+}
+
+impl MetallicSmoothness<'_> {
+    /// glTF `metallicRoughnessTexture` with roughness in G and metalness in B channel.
+    ///
+    /// Returns `None` when neither metalness nor smoothness come from a texture.
+    pub fn to_metallic_roughness(&self) -> Result<Option<RgbImage>> {
+        let smoothness_map = match self.channel {
+            SmoothnessChannel::MetallicAlpha => self.metallic_map,
+            SmoothnessChannel::AlbedoAlpha => self.albedo_map,
+        };
+        if self.metallic_map.is_none() && smoothness_map.is_none() {
+            return Ok(None);
+        }
+        let metallic_map = self.metallic_map.map(open_rgba).transpose()?;
+        let smoothness_map = match self.channel {
+            SmoothnessChannel::MetallicAlpha => None,
+            SmoothnessChannel::AlbedoAlpha => smoothness_map.map(open_rgba).transpose()?,
+        };
+        let (width, height) = metallic_map
+            .as_ref()
+            .or(smoothness_map.as_ref())
+            .map(RgbaImage::dimensions)
+            .unwrap_or_default();
+        let smoothness_map = smoothness_map.map(|map| resize(map, width, height));
+        let smoothness_map = match self.channel {
+            SmoothnessChannel::MetallicAlpha => metallic_map.as_ref(),
+            SmoothnessChannel::AlbedoAlpha => smoothness_map.as_ref(),
+        };
+
+        let metallic = to_byte(self.metallic);
+        let result = RgbImage::from_fn(width, height, |x, y| {
+            let metallic = metallic_map
+                .as_ref()
+                .map(|map| map.get_pixel(x, y)[0])
+                .unwrap_or(metallic);
+            let smoothness = smoothness_map
+                .map(|map| map.get_pixel(x, y)[3] as f32 / 255.0 * self.smoothness_scale)
+                .unwrap_or(self.smoothness);
+            let roughness = to_byte(1.0 - smoothness);
+            image::Rgb([255, roughness, metallic])
+        });
+        Ok(Some(result))
+    }
+}
+
+fn open_rgba(path: &Path) -> Result<RgbaImage> {
+    let image = image::open(path).with_context(|| format!("failed to read {}", path.display()))?;
+    Ok(image.to_rgba8())
+}
+
+fn resize(image: RgbaImage, width: u32, height: u32) -> RgbaImage {
+    if image.dimensions() == (width, height) {
+        return image;
+    }
+    imageops::resize(&image, width, height, imageops::FilterType::Triangle)
+}
+
+fn to_byte(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// Saves `pixels` as a single row PNG in a temporary directory.
+    fn save_png(name: &str, pixels: &[[u8; 4]]) -> PathBuf {
+        let dir = std::env::temp_dir().join("lwa_unity_unpack_texture_tests");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        RgbaImage::from_fn(pixels.len() as u32, 1, |x, _| {
+            image::Rgba(pixels[x as usize])
+        })
+        .save(&path)
+        .unwrap();
+        path
+    }
+
+    #[test]
+    fn no_maps_no_texture() {
+        let setup = MetallicSmoothness {
+            metallic: 1.0,
+            smoothness: 0.5,
+            ..Default::default()
+        };
+        assert!(setup.to_metallic_roughness().unwrap().is_none());
+    }
+
+    #[test]
+    fn metallic_map_alpha_is_scaled_smoothness() {
+        let metallic_map = save_png("metallic.png", &[[255, 0, 0, 255], [0, 0, 0, 0]]);
+        let setup = MetallicSmoothness {
+            metallic_map: Some(&metallic_map),
+            smoothness: 0.9,
+            smoothness_scale: 0.5,
+            ..Default::default()
+        };
+        let result = setup.to_metallic_roughness().unwrap().unwrap();
+        assert_eq!(result.dimensions(), (2, 1));
+        assert_eq!(result.get_pixel(0, 0).0, [255, 128, 255]);
+        assert_eq!(result.get_pixel(1, 0).0, [255, 255, 0]);
+    }
+
+    #[test]
+    fn albedo_alpha_smoothness() {
+        let albedo_map = save_png("albedo.png", &[[10, 20, 30, 255]]);
+        let setup = MetallicSmoothness {
+            albedo_map: Some(&albedo_map),
+            channel: SmoothnessChannel::AlbedoAlpha,
+            metallic: 0.25,
+            smoothness_scale: 1.0,
+            ..Default::default()
+        };
+        let result = setup.to_metallic_roughness().unwrap().unwrap();
+        assert_eq!(result.get_pixel(0, 0).0, [255, 0, 64]);
+    }
+
+    #[test]
+    fn missing_albedo_uses_smoothness_value() {
+        let metallic_map = save_png("metallic_opaque.png", &[[51, 0, 0, 255]]);
+        let setup = MetallicSmoothness {
+            metallic_map: Some(&metallic_map),
+            channel: SmoothnessChannel::AlbedoAlpha,
+            smoothness: 0.8,
+            smoothness_scale: 1.0,
+            ..Default::default()
+        };
+        let result = setup.to_metallic_roughness().unwrap().unwrap();
+        assert_eq!(result.get_pixel(0, 0).0, [255, 51, 51]);
+    }
+
+    #[test]
+    fn channel_property() {
+        assert_eq!(
+            SmoothnessChannel::from_property(0.0),
+            SmoothnessChannel::MetallicAlpha
+        );
+        assert_eq!(
+            SmoothnessChannel::from_property(1.0),
+            SmoothnessChannel::AlbedoAlpha
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use crate::asset::{Asset, AssetType};
+use crate::package::Package;
+use anyhow::Result;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io;
+
+/// Differences between two versions of the same package, entries are paired by GUID.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct PackageDiff {
+    pub added: Vec<Asset>,
+    pub removed: Vec<Asset>,
+    /// Same GUID with a new pathname.
+    pub moved: Vec<MovedAsset>,
+    /// Same GUID with different `asset` or `asset.meta` content.
+    pub changed: Vec<ChangedAsset>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MovedAsset {
+    pub guid: String,
+    pub old_pathname: String,
+    pub new_pathname: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ChangedAsset {
+    pub guid: String,
+    pub pathname: String,
+    pub asset_changed: bool,
+    pub meta_changed: bool,
+    pub old_size: u64,
+    pub new_size: u64,
+}
+
+impl PackageDiff {
+    pub fn new(old: &Package, new: &Package) -> Result<PackageDiff> {
+        let old_hashes = PackageHashes::new(old)?;
+        let new_hashes = PackageHashes::new(new)?;
+        Ok(PackageDiff::compare(
+            old.assets(),
+            &old_hashes,
+            new.assets(),
+            &new_hashes,
+        ))
+    }
+
+    /// Pairs the assets of both versions by GUID, content is compared with the hashes.
+    fn compare(
+        old: &[Asset],
+        old_hashes: &PackageHashes,
+        new: &[Asset],
+        new_hashes: &PackageHashes,
+    ) -> PackageDiff {
+        let old_assets: HashMap<&str, &Asset> = old.iter().map(|a| (a.guid.as_str(), a)).collect();
+        let new_guids: HashSet<&str> = new.iter().map(|a| a.guid.as_str()).collect();
+        let mut diff = PackageDiff::default();
+
+        for new_asset in new {
+            let Some(old_asset) = old_assets.get(new_asset.guid.as_str()) else {
+                diff.added.push(new_asset.clone());
+                continue;
+            };
+            if old_asset.pathname != new_asset.pathname {
+                diff.moved.push(MovedAsset {
+                    guid: new_asset.guid.clone(),
+                    old_pathname: old_asset.pathname.clone(),
+                    new_pathname: new_asset.pathname.clone(),
+                });
+            }
+            let guid = &new_asset.guid;
+            let asset_changed = old_hashes.assets.get(guid) != new_hashes.assets.get(guid);
+            let meta_changed = old_hashes.metas.get(guid) != new_hashes.metas.get(guid);
+            if asset_changed || meta_changed {
+                diff.changed.push(ChangedAsset {
+                    guid: guid.clone(),
+                    pathname: new_asset.pathname.clone(),
+                    asset_changed,
+                    meta_changed,
+                    old_size: old_asset.size,
+                    new_size: new_asset.size,
+                });
+            }
+        }
+        diff.removed = old
+            .iter()
+            .filter(|a| !new_guids.contains(a.guid.as_str()))
+            .cloned()
+            .collect();
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.moved.is_empty()
+            && self.changed.is_empty()
+    }
+}
+
+impl fmt::Display for PackageDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for asset in &self.added {
+            writeln!(f, "+ {} ({})", asset.pathname, asset.guid)?;
+        }
+        for asset in &self.removed {
+            writeln!(f, "- {} ({})", asset.pathname, asset.guid)?;
+        }
+        for asset in &self.moved {
+            writeln!(
+                f,
+                "> {} -> {} ({})",
+                asset.old_pathname, asset.new_pathname, asset.guid
+            )?;
+        }
+        for asset in &self.changed {
+            let what = match (asset.asset_changed, asset.meta_changed) {
+                (true, true) => "asset, meta",
+                (true, false) => "asset",
+                _ => "meta",
+            };
+            writeln!(f, "~ {} [{}] ({})", asset.pathname, what, asset.guid)?;
+        }
+        writeln!(
+            f,
+            "{} added, {} removed, {} moved, {} changed",
+            self.added.len(),
+            self.removed.len(),
+            self.moved.len(),
+            self.changed.len()
+        )
+    }
+}
+
+/// SHA-256 of the package content by GUID.
+#[derive(Debug, Default)]
+struct PackageHashes {
+    /// Asset bodies, folders are skipped as they have none.
+    assets: HashMap<String, String>,
+    metas: HashMap<String, String>,
+}
+
+impl PackageHashes {
+    fn new(package: &Package) -> Result<PackageHashes> {
+        let mut hashes = PackageHashes::default();
+        package.for_each_asset(
+            |a| a.asset_type != AssetType::Folder,
+            |asset, reader| {
+                let mut hasher = Sha256::new();
+                io::copy(reader, &mut hasher)?;
+                hashes
+                    .assets
+                    .insert(asset.guid.clone(), format!("{:x}", hasher.finalize()));
+                Ok(())
+            },
+        )?;
+        for asset in package.assets() {
+            if let Some(meta) = package.read_meta(&asset.guid) {
+                hashes
+                    .metas
+                    .insert(asset.guid.clone(), hash(meta.as_bytes()));
+            }
+        }
+        Ok(hashes)
+    }
+}
+
+fn hash(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(guid: &str, pathname: &str) -> Asset {
+        Asset::new(guid.to_string(), pathname.to_string())
+    }
+
+    /// Hashes of `(guid, asset content, meta content)` entries.
+    fn hashes(entries: &[(&str, &str, &str)]) -> PackageHashes {
+        let mut hashes = PackageHashes::default();
+        for (guid, asset, meta) in entries {
+            hashes
+                .assets
+                .insert(guid.to_string(), hash(asset.as_bytes()));
+            hashes.metas.insert(guid.to_string(), hash(meta.as_bytes()));
+        }
+        hashes
+    }
+
+    #[test]
+    fn sorts_assets_by_guid() {
+        let old = [
+            asset("a1", "Assets/Same.png"),
+            asset("a2", "Assets/Old.png"),
+            asset("a3", "Assets/Removed.png"),
+            asset("a4", "Assets/Meta.png"),
+        ];
+        let new = [
+            asset("a1", "Assets/Same.png"),
+            asset("a2", "Assets/Moved/Old.png"),
+            asset("a4", "Assets/Meta.png"),
+            asset("a5", "Assets/Added.png"),
+        ];
+        let old_hashes = hashes(&[
+            ("a1", "same", "meta"),
+            ("a2", "old", "meta"),
+            ("a3", "removed", "meta"),
+            ("a4", "same", "meta"),
+        ]);
+        let new_hashes = hashes(&[
+            ("a1", "same", "meta"),
+            ("a2", "new", "meta"),
+            ("a4", "same", "new meta"),
+            ("a5", "added", "meta"),
+        ]);
+        let diff = PackageDiff::compare(&old, &old_hashes, &new, &new_hashes);
+
+        let guids =
+            |assets: &[Asset]| -> Vec<String> { assets.iter().map(|a| a.guid.clone()).collect() };
+        assert_eq!(guids(&diff.added), ["a5"]);
+        assert_eq!(guids(&diff.removed), ["a3"]);
+        assert_eq!(diff.moved.len(), 1);
+        assert_eq!(diff.moved[0].old_pathname, "Assets/Old.png");
+        assert_eq!(diff.moved[0].new_pathname, "Assets/Moved/Old.png");
+        let changed: Vec<(&str, bool, bool)> = diff
+            .changed
+            .iter()
+            .map(|c| (c.guid.as_str(), c.asset_changed, c.meta_changed))
+            .collect();
+        assert_eq!(changed, [("a2", true, false), ("a4", false, true)]);
+    }
+
+    #[test]
+    fn same_package_is_empty() {
+        let assets = [
+            asset("f1", "Assets/Folder"),
+            asset("a1", "Assets/Folder/A.mat"),
+        ];
+        // folders have no asset body, only meta
+        let mut hashes = hashes(&[("a1", "material", "meta")]);
+        hashes.metas.insert("f1".to_string(), hash(b"folder meta"));
+        let diff = PackageDiff::compare(&assets, &hashes, &assets, &hashes);
+        assert!(diff.is_empty());
+    }
+}
@@ -0,0 +1,152 @@
+use crate::asset::{Asset, AssetType};
+use regex::Regex;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
+/// Selects package entries by pathname, type and extension.
+#[derive(Clone, Debug, Default)]
+pub struct AssetFilter {
+    /// when not empty asset pathname has to match at least one of the patterns
+    pub include: Vec<PathPattern>,
+    /// assets matching any of the patterns are skipped
+    pub exclude: Vec<PathPattern>,
+    /// when not empty asset has to be one of the types
+    pub include_types: Vec<AssetType>,
+    /// assets of these types are skipped
+    pub exclude_types: Vec<AssetType>,
+    /// assets with these extensions are skipped
+    pub ignore_extensions: Vec<String>,
+    /// when set only assets with these GUIDs are kept
+    pub guids: Option<BTreeSet<String>>,
+}
+
+impl AssetFilter {
+    pub fn matches(&self, asset: &Asset) -> bool {
+        if let Some(guids) = &self.guids {
+            if !guids.contains(&asset.guid) {
+                return false;
+            }
+        }
+        let extension = asset.extension.as_deref().unwrap_or_default();
+        if self.ignore_extensions.iter().any(|e| e == extension) {
+            return false;
+        }
+        if self.exclude_types.contains(&asset.asset_type)
+            || (!self.include_types.is_empty() && !self.include_types.contains(&asset.asset_type))
+        {
+            return false;
+        }
+        if self.exclude.iter().any(|p| p.matches(&asset.pathname)) {
+            return false;
+        }
+        self.include.is_empty() || self.include.iter().any(|p| p.matches(&asset.pathname))
+    }
+}
+
+/// Glob like pattern for pathnames.
+///
+/// `*` matches anything except `/`, `**` matches anything (`**/` also no folder at all) and `?`
+/// matches single character.
+/// Pattern matches the pathname itself and everything inside of it, so `Assets/Environment/Rocks`
+/// selects the whole folder.
+#[derive(Clone, Debug)]
+pub struct PathPattern {
+    pattern: String,
+    regex: Regex,
+}
+
+impl PathPattern {
+    pub fn matches(&self, pathname: &str) -> bool {
+        self.regex.is_match(pathname)
+    }
+}
+
+impl FromStr for PathPattern {
+    type Err = regex::Error;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        let trimmed = pattern.trim_end_matches('/');
+        let mut regex = String::from("^");
+        let mut chars = trimmed.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    // `**/` also matches no folder at all
+                    if chars.peek() == Some(&'/') {
+                        chars.next();
+                        regex.push_str("(.*/)?");
+                    } else {
+                        regex.push_str(".*");
+                    }
+                }
+                '*' => regex.push_str("[^/]*"),
+                '?' => regex.push_str("[^/]"),
+                c => regex.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        regex.push_str("(/.*)?$");
+        Ok(PathPattern {
+            pattern: pattern.to_string(),
+            regex: Regex::new(&regex)?,
+        })
+    }
+}
+
+impl fmt::Display for PathPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.pattern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(pattern: &str) -> PathPattern {
+        pattern.parse().unwrap()
+    }
+
+    #[test]
+    fn matches_folder_content() {
+        let rocks = pattern("Assets/Environment/Rocks/");
+        assert!(rocks.matches("Assets/Environment/Rocks"));
+        assert!(rocks.matches("Assets/Environment/Rocks/Rock.fbx"));
+        assert!(rocks.matches("Assets/Environment/Rocks/Big/Rock.fbx"));
+        assert!(!rocks.matches("Assets/Environment/RocksOld/Rock.fbx"));
+        assert!(!rocks.matches("Assets/Environment"));
+        assert!(!rocks.matches("Other/Assets/Environment/Rocks"));
+    }
+
+    #[test]
+    fn single_star_stays_in_folder() {
+        let materials = pattern("Assets/*.mat");
+        assert!(materials.matches("Assets/Rock.mat"));
+        assert!(!materials.matches("Assets/Materials/Rock.mat"));
+        let rock = pattern("Assets/Rock?.png");
+        assert!(rock.matches("Assets/Rock1.png"));
+        assert!(!rock.matches("Assets/Rock.png"));
+        assert!(!pattern("Assets?Rock.png").matches("Assets/Rock.png"));
+    }
+
+    #[test]
+    fn double_star_crosses_folders() {
+        let prefabs = pattern("Assets/**/*.prefab");
+        assert!(prefabs.matches("Assets/Tree.prefab"));
+        assert!(prefabs.matches("Assets/Environment/Trees/Tree.prefab"));
+        assert!(!prefabs.matches("Packages/Tree.prefab"));
+        let textures = pattern("Assets/**Textures");
+        assert!(textures.matches("Assets/Environment/Textures/Rock.png"));
+        assert!(textures.matches("Assets/Textures"));
+    }
+
+    #[test]
+    fn escapes_regex_characters() {
+        let pattern = pattern("Assets/Rock (Old)+[1].png");
+        assert!(pattern.matches("Assets/Rock (Old)+[1].png"));
+        assert!(!pattern.matches("Assets/Rock Old)1.png"));
+        assert!(!pattern.matches("Assets/Rock (Old)+[1]xpng"));
+        assert!(!pattern.matches("Assets/Rock (Old)))+[1].png"));
+    }
+}
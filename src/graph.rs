@@ -0,0 +1,490 @@
+use crate::asset::{Asset, AssetType};
+use crate::package::Package;
+use crate::yaml_helpers::{parse_unity_documents, quote_guids, UnityDocument};
+use anyhow::Result;
+use rayon::prelude::*;
+use serde::Serialize;
+use serde_yaml::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{self, Write};
+
+/// Extensions of the assets Unity stores as YAML when using text serialization.
+pub const YAML_EXTENSIONS: &[&str] = &[
+    "anim",
+    "asset",
+    "brush",
+    "controller",
+    "cubemap",
+    "flare",
+    "fontsettings",
+    "giparams",
+    "guiskin",
+    "lighting",
+    "mask",
+    "mat",
+    "mixer",
+    "overrideController",
+    "physicMaterial",
+    "physicsMaterial2D",
+    "playable",
+    "prefab",
+    "preset",
+    "renderTexture",
+    "shadervariants",
+    "signal",
+    "spriteatlas",
+    "terrainlayer",
+    "unity",
+];
+
+pub fn is_yaml_asset(asset: &Asset) -> bool {
+    asset
+        .extension
+        .as_deref()
+        .is_some_and(|e| YAML_EXTENSIONS.contains(&e))
+}
+
+/// Single `{fileID, guid, type}` reference from one asset to another.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    /// GUID of the asset containing the reference.
+    pub from: String,
+    /// Referenced GUID, it can point outside of the package.
+    pub to: String,
+    pub file_id: i64,
+    #[serde(rename = "type")]
+    pub reference_type: Option<i64>,
+    /// Path of the referencing field, e.g. `MeshRenderer.m_Materials[0]`.
+    pub field: String,
+    /// Reference comes from the `.meta` file instead of the asset itself.
+    pub from_meta: bool,
+}
+
+/// GUID to GUID references between the package assets.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct DependencyGraph {
+    /// Pathnames of the package assets by GUID.
+    pub pathnames: BTreeMap<String, String>,
+    pub references: Vec<Reference>,
+    /// Class IDs missing from the registry, with the type names found in the YAML objects.
+    pub unknown_classes: BTreeMap<u32, String>,
+}
+
+impl DependencyGraph {
+    /// Reads all YAML assets and meta files of the package.
+    pub fn build(package: &Package) -> Result<DependencyGraph> {
+        let mut contents = vec![];
+        package.for_each_asset(is_yaml_asset, |asset, reader| {
+            let mut content = String::new();
+            // binary serialized assets are skipped
+            if reader.read_to_string(&mut content).is_ok() && content.starts_with("%YAML") {
+                contents.push((asset.guid.clone(), content));
+            }
+            Ok(())
+        })?;
+        let documents: Vec<(&String, Vec<UnityDocument>)> = contents
+            .par_iter()
+            .map(|(guid, content)| {
+                let parsed = parse_unity_documents(content);
+                for e in parsed.errors {
+                    eprintln!("Failed to parse object of {}: {}", guid, e);
+                }
+                (guid, parsed.documents)
+            })
+            .collect();
+        let mut references: Vec<Reference> = documents
+            .par_iter()
+            .flat_map(|(guid, documents)| document_references(guid, documents))
+            .collect();
+        let unknown_classes = documents
+            .iter()
+            .flat_map(|(_, documents)| documents)
+            .filter(|d| d.class_name().is_none())
+            .map(|d| (d.class_id, d.type_name.clone()))
+            .collect();
+        for asset in package.assets() {
+            let Some(meta) = package.read_meta(&asset.guid) else {
+                continue;
+            };
+            match meta_references(&asset.guid, meta) {
+                Ok(meta_references) => references.extend(meta_references),
+                Err(e) => eprintln!("Failed to parse meta of {}: {}", asset.pathname, e),
+            }
+        }
+
+        let pathnames: BTreeMap<String, String> = package
+            .assets()
+            .iter()
+            .map(|a| (a.guid.clone(), a.pathname.clone()))
+            .collect();
+        references.sort_by(|a, b| {
+            (pathnames.get(&a.from), &a.field).cmp(&(pathnames.get(&b.from), &b.field))
+        });
+
+        Ok(DependencyGraph {
+            pathnames,
+            references,
+            unknown_classes,
+        })
+    }
+
+    /// References going out of the asset.
+    pub fn dependencies<'a>(&'a self, guid: &'a str) -> impl Iterator<Item = &'a Reference> {
+        self.references.iter().filter(move |r| r.from == guid)
+    }
+
+    /// References pointing to the asset.
+    pub fn dependents<'a>(&'a self, guid: &'a str) -> impl Iterator<Item = &'a Reference> {
+        self.references.iter().filter(move |r| r.to == guid)
+    }
+
+    /// GUIDs of the roots and every asset they reference, directly or through other assets.
+    pub fn closure<'a>(&self, roots: impl IntoIterator<Item = &'a str>) -> BTreeSet<String> {
+        let mut dependencies: HashMap<&str, Vec<&str>> = HashMap::new();
+        for reference in &self.references {
+            dependencies
+                .entry(reference.from.as_str())
+                .or_default()
+                .push(reference.to.as_str());
+        }
+        let mut visited = BTreeSet::new();
+        let mut queue: Vec<&str> = roots.into_iter().collect();
+        while let Some(guid) = queue.pop() {
+            if !visited.insert(guid.to_string()) {
+                continue;
+            }
+            if let Some(next) = dependencies.get(guid) {
+                queue.extend(next.iter().filter(|g| !visited.contains(**g)));
+            }
+        }
+        visited
+    }
+
+    /// GUIDs of the root assets with every asset they use, directly or through other assets.
+    /// Empty when the package has no root assets, see [`root_assets`].
+    pub fn used_assets(&self, package: &Package) -> BTreeSet<String> {
+        self.closure(root_assets(package).iter().map(|a| a.guid.as_str()))
+    }
+
+    fn label<'a>(&'a self, guid: &'a str) -> &'a str {
+        self.pathnames.get(guid).map(String::as_str).unwrap_or(guid)
+    }
+
+    /// Graphviz DOT representation, nodes are labeled with pathnames.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph dependencies {\n  rankdir=LR;\n  node [shape=box];\n");
+        for guid in self.used_guids() {
+            let _ = writeln!(dot, "  \"{}\" [label={:?}];", guid, self.label(guid));
+        }
+        for reference in &self.references {
+            let _ = writeln!(
+                dot,
+                "  \"{}\" -> \"{}\" [label={:?}];",
+                reference.from, reference.to, reference.field
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Mermaid flowchart representation, nodes are labeled with pathnames.
+    pub fn to_mermaid(&self) -> String {
+        let mut mermaid = String::from("flowchart LR\n");
+        for guid in self.used_guids() {
+            let label = self.label(guid).replace('"', "#quot;");
+            let _ = writeln!(mermaid, "  g{}[\"{}\"]", guid, label);
+        }
+        for reference in &self.references {
+            let field = reference.field.replace('"', "#quot;");
+            let _ = writeln!(
+                mermaid,
+                "  g{} -->|\"{}\"| g{}",
+                reference.from, field, reference.to
+            );
+        }
+        mermaid
+    }
+
+    fn used_guids(&self) -> Vec<&str> {
+        let mut guids: Vec<&str> = self
+            .references
+            .iter()
+            .flat_map(|r| [r.from.as_str(), r.to.as_str()])
+            .collect();
+        guids.sort_unstable();
+        guids.dedup();
+        guids
+    }
+}
+
+/// Assets not used by the package and references pointing outside of it.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct OrphanReport {
+    /// Package has no scenes, prefabs, materials or models, so unused assets can't be found.
+    pub no_roots: bool,
+    /// Assets not used by any of the [`root_assets`], directly or through other assets.
+    pub unreferenced: Vec<Asset>,
+    /// References to GUIDs that are not part of the package.
+    pub missing: Vec<MissingReference>,
+    /// Class IDs missing from the registry, with the type names found in the YAML objects.
+    pub unknown_classes: BTreeMap<u32, String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MissingReference {
+    /// Pathname of the asset containing the reference.
+    pub pathname: String,
+    #[serde(flatten)]
+    pub reference: Reference,
+    /// Reference points to Unity built-in resources instead of other package.
+    pub builtin: bool,
+}
+
+impl OrphanReport {
+    pub fn new(package: &Package, graph: &DependencyGraph) -> OrphanReport {
+        let no_roots = root_assets(package).is_empty();
+        let used = graph.used_assets(package);
+        let unreferenced = package
+            .assets()
+            .iter()
+            .filter(|a| !no_roots && a.asset_type != AssetType::Folder && !used.contains(&a.guid))
+            .cloned()
+            .collect();
+        let missing = graph
+            .references
+            .iter()
+            .filter(|r| !graph.pathnames.contains_key(&r.to))
+            .map(|r| MissingReference {
+                pathname: graph.label(&r.from).to_string(),
+                reference: r.clone(),
+                builtin: is_builtin_guid(&r.to),
+            })
+            .collect();
+        OrphanReport {
+            no_roots,
+            unreferenced,
+            missing,
+            unknown_classes: graph.unknown_classes.clone(),
+        }
+    }
+}
+
+impl fmt::Display for OrphanReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Unreferenced assets:")?;
+        if self.no_roots {
+            writeln!(
+                f,
+                "  no scenes, prefabs, materials or models to check the usage"
+            )?;
+        }
+        for asset in &self.unreferenced {
+            writeln!(f, "  {} ({})", asset.pathname, asset.guid)?;
+        }
+        writeln!(f, "Missing references:")?;
+        for missing in &self.missing {
+            writeln!(
+                f,
+                "  {} {} -> {}{}",
+                missing.pathname,
+                missing.reference.field,
+                missing.reference.to,
+                if missing.builtin { " (built-in)" } else { "" }
+            )?;
+        }
+        if !self.unknown_classes.is_empty() {
+            writeln!(f, "Unknown class IDs:")?;
+            for (class_id, type_name) in &self.unknown_classes {
+                writeln!(f, "  {} ({})", class_id, type_name)?;
+            }
+        }
+        writeln!(
+            f,
+            "{} unreferenced assets, {} missing references",
+            self.unreferenced.len(),
+            self.missing.len()
+        )
+    }
+}
+
+/// Scenes and prefabs, or materials and models for the packages without scenes and prefabs.
+///
+/// Materials and models of packages with scenes or prefabs are not roots, the ones no scene or
+/// prefab uses are unused together with the textures and shaders only they reference.
+pub fn root_assets(package: &Package) -> Vec<&Asset> {
+    let with_type = |types: &[AssetType]| -> Vec<&Asset> {
+        package
+            .assets()
+            .iter()
+            .filter(|a| types.contains(&a.asset_type))
+            .collect()
+    };
+    let roots = with_type(&[AssetType::Scene, AssetType::Prefab]);
+    if !roots.is_empty() {
+        return roots;
+    }
+    with_type(&[AssetType::Material, AssetType::FbxModel])
+}
+
+/// Unity built-in resources use GUIDs like `0000000000000000f000000000000000`.
+pub fn is_builtin_guid(guid: &str) -> bool {
+    guid.starts_with("0000000000000000")
+}
+
+/// References found in Unity YAML asset (prefab, scene, material, etc.) with given GUID.
+///
+/// Malformed objects are skipped, it fails only when none of the objects could be parsed.
+pub fn yaml_references(guid: &str, content: &str) -> Result<Vec<Reference>> {
+    let parsed = parse_unity_documents(content);
+    if parsed.documents.is_empty() {
+        if let Some(e) = parsed.errors.into_iter().next() {
+            return Err(e);
+        }
+    }
+    Ok(document_references(guid, &parsed.documents))
+}
+
+/// References found in the parsed objects of Unity YAML asset with given GUID.
+pub fn document_references(guid: &str, documents: &[UnityDocument]) -> Vec<Reference> {
+    let mut objects: Vec<&UnityDocument> = documents.iter().collect();
+    objects.sort_by_key(|o| o.file_id);
+    let mut references = vec![];
+    for object in objects {
+        let Value::Mapping(fields) = &object.value else {
+            continue;
+        };
+        for (key, value) in fields {
+            if let Some(key) = key.as_str() {
+                let field = format!("{}.{}", object.type_name, key);
+                collect_references(guid, value, &field, false, &mut references);
+            }
+        }
+    }
+    references
+}
+
+/// References found in the `.meta` file of the asset with given GUID.
+pub fn meta_references(guid: &str, content: &str) -> Result<Vec<Reference>> {
+    let meta: Value = serde_yaml::from_str(&quote_guids(content))?;
+    let mut references = vec![];
+    collect_references(guid, &meta, "", true, &mut references);
+    Ok(references)
+}
+
+fn collect_references(
+    from: &str,
+    value: &Value,
+    field: &str,
+    from_meta: bool,
+    references: &mut Vec<Reference>,
+) {
+    match value {
+        Value::Mapping(map) => {
+            let guid = map.get("guid").and_then(Value::as_str);
+            let file_id = map.get("fileID").and_then(as_file_id);
+            if let (Some(guid), Some(file_id)) = (guid, file_id) {
+                if guid != from {
+                    references.push(Reference {
+                        from: from.to_string(),
+                        to: guid.to_string(),
+                        file_id,
+                        reference_type: map.get("type").and_then(Value::as_i64),
+                        field: field.to_string(),
+                        from_meta,
+                    });
+                }
+                return;
+            }
+            for (key, value) in map {
+                let key = match key {
+                    Value::String(key) => key.clone(),
+                    other => serde_yaml::to_string(other)
+                        .unwrap_or_default()
+                        .trim()
+                        .to_string(),
+                };
+                let field = if field.is_empty() {
+                    key
+                } else {
+                    format!("{}.{}", field, key)
+                };
+                collect_references(from, value, &field, from_meta, references);
+            }
+        }
+        Value::Sequence(items) => {
+            for (i, item) in items.iter().enumerate() {
+                let field = format!("{}[{}]", field, i);
+                collect_references(from, item, &field, from_meta, references);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// File IDs are 64 bit, but can be written both as signed and unsigned numbers.
+pub(crate) fn as_file_id(value: &Value) -> Option<i64> {
+    value
+        .as_i64()
+        .or_else(|| value.as_u64().map(|id| id as i64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(references: &[(&str, &str)]) -> DependencyGraph {
+        DependencyGraph {
+            references: references
+                .iter()
+                .map(|(from, to)| Reference {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                    file_id: 2100000,
+                    reference_type: Some(2),
+                    field: "MeshRenderer.m_Materials[0]".to_string(),
+                    from_meta: false,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn guids(guids: &[&str]) -> BTreeSet<String> {
+        guids.iter().map(|g| g.to_string()).collect()
+    }
+
+    #[test]
+    fn closure_follows_references() {
+        let graph = graph(&[
+            ("scene", "prefab"),
+            ("prefab", "material"),
+            ("material", "texture"),
+            ("other", "texture"),
+        ]);
+        assert_eq!(
+            graph.closure(["scene"]),
+            guids(&["scene", "prefab", "material", "texture"])
+        );
+        assert_eq!(graph.closure(["material"]), guids(&["material", "texture"]));
+        assert_eq!(graph.closure([]), guids(&[]));
+    }
+
+    #[test]
+    fn closure_stops_at_cycles() {
+        let graph = graph(&[("a", "b"), ("b", "c"), ("c", "a"), ("c", "d")]);
+        assert_eq!(graph.closure(["b"]), guids(&["a", "b", "c", "d"]));
+    }
+
+    #[test]
+    fn closure_keeps_missing_and_builtin_guids() {
+        let builtin = "0000000000000000f000000000000000";
+        let graph = graph(&[("material", builtin), ("material", "missing")]);
+        assert_eq!(
+            graph.closure(["material"]),
+            guids(&["material", builtin, "missing"])
+        );
+        assert_eq!(graph.closure(["unknown"]), guids(&["unknown"]));
+        assert!(is_builtin_guid(builtin));
+        assert!(!is_builtin_guid("missing"));
+    }
+}
@@ -0,0 +1,99 @@
+use crate::asset::{Asset, AssetType};
+use crate::package::Package;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// Summary of the package content, used by the `list` command.
+#[derive(Serialize, Debug, Clone)]
+pub struct PackageInfo {
+    pub asset_count: usize,
+    /// Uncompressed size of all the asset bodies.
+    pub total_size: u64,
+    /// Uncompressed size of all the meta files.
+    pub total_meta_size: u64,
+    pub type_counts: BTreeMap<String, usize>,
+    pub assets: Vec<Asset>,
+}
+
+impl PackageInfo {
+    pub fn new(package: &Package) -> PackageInfo {
+        let assets = package.assets().to_vec();
+        let mut type_counts = BTreeMap::new();
+        for asset in &assets {
+            *type_counts.entry(asset.asset_type.to_string()).or_default() += 1;
+        }
+        PackageInfo {
+            asset_count: assets
+                .iter()
+                .filter(|a| a.asset_type != AssetType::Folder)
+                .count(),
+            total_size: assets.iter().map(|a| a.size).sum(),
+            total_meta_size: assets.iter().map(|a| a.meta_size).sum(),
+            type_counts,
+            assets,
+        }
+    }
+}
+
+impl fmt::Display for PackageInfo {
+    /// Tree view of the pathnames followed by per type counts and sizes.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut printed_dirs = BTreeSet::new();
+        for asset in &self.assets {
+            let parts: Vec<&str> = asset.pathname.split('/').collect();
+            let (name, dirs) = parts.split_last().unwrap();
+            for depth in 0..dirs.len() {
+                let dir = dirs[..=depth].join("/");
+                if printed_dirs.insert(dir) {
+                    writeln!(f, "{}{}/", "  ".repeat(depth), dirs[depth])?;
+                }
+            }
+            let indent = "  ".repeat(dirs.len());
+            if asset.asset_type == AssetType::Folder {
+                if printed_dirs.insert(asset.pathname.clone()) {
+                    writeln!(f, "{}{}/", indent, name)?;
+                }
+                continue;
+            }
+            write!(
+                f,
+                "{}{} [{}] {}",
+                indent,
+                name,
+                asset.asset_type,
+                format_size(asset.size)
+            )?;
+            if asset.has_meta {
+                write!(f, " (meta {})", format_size(asset.meta_size))?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f)?;
+        for (asset_type, count) in &self.type_counts {
+            writeln!(f, "{}: {}", asset_type, count)?;
+        }
+        writeln!(
+            f,
+            "Total: {} assets, {} (meta files {})",
+            self.asset_count,
+            format_size(self.total_size),
+            format_size(self.total_meta_size)
+        )
+    }
+}
+
+/// Formats byte count using binary units, e.g. `1.5 KiB`.
+pub fn format_size(size: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if size < 1024 {
+        return format!("{} B", size);
+    }
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
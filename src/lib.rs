@@ -0,0 +1,18 @@
+mod archive;
+pub mod asset;
+pub mod convert;
+pub mod diff;
+pub mod filter;
+pub mod graph;
+pub mod inspect;
+pub mod material_export;
+pub mod pack;
+pub mod package;
+pub mod primitives;
+pub mod shader;
+pub mod unpacker;
+pub mod yaml_helpers;
+
+pub use asset::{Asset, AssetType};
+pub use package::Package;
+pub use unpacker::{UnpackOptions, Unpacker};
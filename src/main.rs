@@ -1,19 +1,162 @@
 mod args;
-pub mod asset;
-pub mod primitives;
-mod unpacker;
-mod yaml_helpers;
-use clap::Parser;
+use crate::args::{
+    Args, Cli, Command, DiffArgs, GraphArgs, GraphFormat, ListArgs, OutputFormat, PackArgs,
+    RepackArgs,
+};
+use anyhow::Context;
+use clap::{CommandFactory, Parser};
+use lwa_unity_unpack::convert::mapping::ShaderMappings;
+use lwa_unity_unpack::diff::PackageDiff;
+use lwa_unity_unpack::graph::{root_assets, DependencyGraph, OrphanReport};
+use lwa_unity_unpack::inspect::PackageInfo;
+use lwa_unity_unpack::pack::pack_directory;
+use lwa_unity_unpack::shader::ShaderReport;
+use lwa_unity_unpack::{Package, Unpacker};
 
 fn main() {
-    let args = crate::args::Args::parse();
-    let mut unpacker = crate::unpacker::Unpacker {
-        args,
-        assets: vec![],
+    let cli = Cli::parse();
+    let result = match (cli.command, cli.unpack) {
+        (Some(Command::Unpack(args)), _) | (None, Some(args)) => unpack(args),
+        (Some(Command::List(args)), _) => list(args),
+        (Some(Command::Pack(args)), _) => pack(args),
+        (Some(Command::Repack(args)), _) => repack(args),
+        (Some(Command::Diff(args)), _) => diff(args),
+        (Some(Command::Graph(args)), _) => graph(args),
+        (Some(Command::Orphans(args)), _) => orphans(args),
+        (Some(Command::Shaders(args)), _) => shaders(args),
+        (None, None) => Cli::command().print_help().map_err(Into::into),
     };
+    if let Err(e) = result {
+        eprintln!("Error: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+fn unpack(args: Args) -> anyhow::Result<()> {
+    args.check();
+    let mut options = args.unpack_options();
+    if let Some(path) = &args.shader_mapping {
+        options.shader_mappings = ShaderMappings::load(path)?;
+    }
+    if !args.root.is_empty() || args.skip_unreferenced {
+        let package = Package::open(&args.input)?;
+        let graph = DependencyGraph::build(&package)?;
+        let mut guids = None;
+        if !args.root.is_empty() {
+            let roots: Vec<&str> = package
+                .assets()
+                .iter()
+                .filter(|a| args.root.iter().any(|p| p.matches(&a.pathname)))
+                .map(|a| a.guid.as_str())
+                .collect();
+            if roots.is_empty() {
+                anyhow::bail!("No assets matching the --root pathnames");
+            }
+            guids = Some(graph.closure(roots));
+        }
+        if args.skip_unreferenced && root_assets(&package).is_empty() {
+            eprintln!("No scenes, prefabs, materials or models, --skip-unreferenced is ignored");
+        } else if args.skip_unreferenced {
+            let used = graph.used_assets(&package);
+            guids = Some(match guids {
+                Some(guids) => guids.intersection(&used).cloned().collect(),
+                None => used,
+            });
+        }
+        options.filter.guids = guids;
+    }
+    let mut unpacker = Unpacker::new(&args.output, options);
 
-    unpacker.prepare_environment();
-    unpacker.extract();
+    unpacker
+        .prepare_environment()
+        .context("Failed to prepare output directory")?;
+    unpacker
+        .extract(&args.input)
+        .context("Failed to extract archive")?;
     unpacker.process_data();
     unpacker.update_gltf_materials();
+    unpacker.export_prefab_scenes();
+    unpacker.export_materials()?;
+    Ok(())
+}
+
+fn list(args: ListArgs) -> anyhow::Result<()> {
+    let package = Package::open(&args.input)?;
+    let info = PackageInfo::new(&package);
+    match args.format {
+        OutputFormat::Text => print!("{}", info),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&info)?),
+    }
+    Ok(())
+}
+
+fn pack(args: PackArgs) -> anyhow::Result<()> {
+    let assets = pack_directory(&args.input, &args.output)?;
+    println!(
+        "Packed {} entries into {}",
+        assets.len(),
+        args.output.display()
+    );
+    Ok(())
+}
+
+fn repack(args: RepackArgs) -> anyhow::Result<()> {
+    let package = Package::open(&args.input)?;
+    let assets = package.repack(&args.output, &args.filter.into())?;
+    println!(
+        "Packed {} entries into {}",
+        assets.len(),
+        args.output.display()
+    );
+    Ok(())
+}
+
+fn diff(args: DiffArgs) -> anyhow::Result<()> {
+    let old = Package::open(&args.old)?;
+    let new = Package::open(&args.new)?;
+    let diff = PackageDiff::new(&old, &new)?;
+    match args.format {
+        OutputFormat::Text => print!("{}", diff),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+    }
+    Ok(())
+}
+
+fn graph(args: GraphArgs) -> anyhow::Result<()> {
+    let package = Package::open(&args.input)?;
+    let graph = DependencyGraph::build(&package)?;
+    match args.format {
+        GraphFormat::Json => println!("{}", serde_json::to_string_pretty(&graph)?),
+        GraphFormat::Dot => print!("{}", graph.to_dot()),
+        GraphFormat::Mermaid => print!("{}", graph.to_mermaid()),
+    }
+    // DOT and Mermaid have no place for them, stderr keeps the output valid
+    if args.format != GraphFormat::Json && !graph.unknown_classes.is_empty() {
+        eprintln!("Unknown class IDs:");
+        for (class_id, type_name) in &graph.unknown_classes {
+            eprintln!("  {} ({})", class_id, type_name);
+        }
+    }
+    Ok(())
+}
+
+fn orphans(args: ListArgs) -> anyhow::Result<()> {
+    let package = Package::open(&args.input)?;
+    let graph = DependencyGraph::build(&package)?;
+    let report = OrphanReport::new(&package, &graph);
+    match args.format {
+        OutputFormat::Text => print!("{}", report),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+    Ok(())
+}
+
+fn shaders(args: ListArgs) -> anyhow::Result<()> {
+    let package = Package::open(&args.input)?;
+    let report = ShaderReport::new(&package)?;
+    match args.format {
+        OutputFormat::Text => print!("{}", report),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+    Ok(())
 }
@@ -0,0 +1,105 @@
+use crate::convert::material::{alpha_mode, MaterialLibrary};
+use crate::primitives::materials::{UnityColor, UnityMaterial, UnityVector2};
+use crate::shader::RenderPipeline;
+use gltf::json::material::AlphaMode;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Material library written as `materials.json`, for tools that don't read Unity YAML.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct MaterialExport {
+    pub materials: Vec<ExportedMaterial>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ExportedMaterial {
+    pub name: String,
+    pub pathname: String,
+    pub guid: String,
+    /// `None` for shaders that are neither known nor included in the package.
+    pub shader: Option<String>,
+    pub pipeline: RenderPipeline,
+    /// Custom queue of the material or the default one of its render mode.
+    pub render_queue: i32,
+    pub keywords: Vec<String>,
+    pub textures: BTreeMap<String, ExportedTexture>,
+    pub floats: BTreeMap<String, f32>,
+    pub colors: BTreeMap<String, UnityColor>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ExportedTexture {
+    pub guid: String,
+    /// `None` for textures missing from the package.
+    pub pathname: Option<String>,
+    pub scale: UnityVector2,
+    pub offset: UnityVector2,
+}
+
+impl MaterialExport {
+    /// `pathnames` are the package pathnames of the assets by GUID.
+    pub fn new(library: &MaterialLibrary, pathnames: &HashMap<String, String>) -> MaterialExport {
+        let mut materials: Vec<ExportedMaterial> = library
+            .materials
+            .iter()
+            .map(|(guid, unity_material)| export_material(library, pathnames, guid, unity_material))
+            .collect();
+        materials.sort_by(|a, b| a.pathname.cmp(&b.pathname));
+        MaterialExport { materials }
+    }
+}
+
+fn export_material(
+    library: &MaterialLibrary,
+    pathnames: &HashMap<String, String>,
+    guid: &str,
+    unity_material: &UnityMaterial,
+) -> ExportedMaterial {
+    let properties = &unity_material.properties;
+    let shader = library.shader(unity_material);
+    let render_queue = match unity_material.custom_render_queue {
+        queue if queue >= 0 => queue,
+        _ => match alpha_mode(unity_material, library.properties(unity_material)).0 {
+            AlphaMode::Opaque => 2000,
+            AlphaMode::Mask => 2450,
+            AlphaMode::Blend => 3000,
+        },
+    };
+    let textures = properties
+        .tex_envs
+        .iter()
+        .flatten()
+        .filter_map(|(name, info)| {
+            let guid = info.texture.guid.clone()?;
+            let texture = ExportedTexture {
+                pathname: pathnames.get(&guid).cloned(),
+                guid,
+                scale: info.scale,
+                offset: info.offset,
+            };
+            Some((name.clone(), texture))
+        })
+        .collect();
+    ExportedMaterial {
+        name: unity_material.name.clone(),
+        pathname: pathnames.get(guid).cloned().unwrap_or_default(),
+        guid: guid.to_string(),
+        shader: shader.name,
+        pipeline: shader.pipeline,
+        render_queue,
+        keywords: unity_material.keywords().map(str::to_string).collect(),
+        textures,
+        floats: properties
+            .floats
+            .iter()
+            .flatten()
+            .map(|(name, value)| (name.clone(), *value))
+            .collect(),
+        colors: properties
+            .colors
+            .iter()
+            .flatten()
+            .map(|(name, value)| (name.clone(), *value))
+            .collect(),
+    }
+}
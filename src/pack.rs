@@ -0,0 +1,283 @@
+use crate::archive;
+use crate::asset::{Asset, AssetType};
+use crate::unpacker::MATERIALS_FILE_NAME;
+use anyhow::{bail, Context, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Creates `.unitypackage` from the directory with Unity assets, reverse of the [`crate::Unpacker`].
+///
+/// Pathnames are relative to `input_dir`, when it points to the `Assets` or `Packages` folder
+/// itself its parent is used instead, so pathnames keep the `Assets/` or `Packages/` prefix Unity
+/// requires. Existing `.meta` files are packed as they are, other files and folders get generated
+/// meta with new GUID, the importer is picked from the file extension.
+///
+/// Files written by unpacking (`materials.json` and `<name>.prefab.glb`) are skipped with a
+/// warning, so the unpacked directory can be packed back.
+pub fn pack_directory(input_dir: &Path, output: &Path) -> Result<Vec<Asset>> {
+    if !input_dir.is_dir() {
+        bail!("Input directory does not exits: {}", input_dir.display());
+    }
+    let root = match input_dir.file_name() {
+        Some(name) if name == "Assets" || name == "Packages" => {
+            input_dir.parent().unwrap_or(input_dir)
+        }
+        _ => input_dir,
+    };
+    let mut paths = vec![];
+    collect_entries(input_dir, &mut paths)?;
+    let mut entries = Vec::with_capacity(paths.len());
+    for path in paths {
+        let pathname = pathname(root, &path)?;
+        if is_unpack_artifact(&pathname) {
+            eprintln!("Skipping {}, it is created by unpacking", pathname);
+            continue;
+        }
+        if !is_project_pathname(&pathname) {
+            bail!(
+                "{} is outside of the Assets and Packages folders, Unity can't import it",
+                pathname
+            );
+        }
+        if pathname != "Assets" && pathname != "Packages" {
+            entries.push((path, pathname));
+        }
+    }
+
+    let mut builder = archive::create(output)?;
+    let mut assets = Vec::with_capacity(entries.len());
+    let mut used_guids = HashSet::new();
+    for (path, pathname) in entries {
+        let meta = match read_meta(&path)? {
+            Some(meta) => meta,
+            None => generate_meta(&path),
+        };
+        let guid = meta_guid(&meta)
+            .with_context(|| format!("Missing guid in meta file of {}", pathname))?;
+        if !used_guids.insert(guid.clone()) {
+            bail!("Duplicated guid {} in meta file of {}", guid, pathname);
+        }
+        println!("{}: {:?}", guid, pathname);
+
+        let mut asset = Asset::new(guid.clone(), pathname.clone());
+        if path.is_dir() {
+            asset.asset_type = AssetType::Folder;
+        } else {
+            builder.append_path_with_name(&path, format!("{}/asset", guid))?;
+            asset.size = fs::metadata(&path)?.len();
+        }
+        archive::append_bytes(&mut builder, &guid, "asset.meta", meta.as_bytes())?;
+        archive::append_bytes(&mut builder, &guid, "pathname", pathname.as_bytes())?;
+        asset.set_meta(&meta);
+        assets.push(asset);
+    }
+    archive::finish(builder)?;
+
+    Ok(assets)
+}
+
+/// `materials.json` and prefab glb files exported by the [`crate::Unpacker`].
+fn is_unpack_artifact(pathname: &str) -> bool {
+    pathname == MATERIALS_FILE_NAME || pathname.ends_with(".prefab.glb")
+}
+
+/// Unity imports only pathnames inside of the `Assets` and `Packages` folders.
+fn is_project_pathname(pathname: &str) -> bool {
+    ["Assets", "Packages"].iter().any(|folder| {
+        pathname == *folder
+            || pathname
+                .strip_prefix(folder)
+                .is_some_and(|rest| rest.starts_with('/'))
+    })
+}
+
+/// Recursively lists files and folders, skipping `.meta` files and hidden entries.
+fn collect_entries(dir: &Path, entries: &mut Vec<PathBuf>) -> Result<()> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<_>>()?;
+    paths.sort();
+    for path in paths {
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        if file_name.starts_with('.') || file_name.ends_with(".meta") {
+            continue;
+        }
+        entries.push(path.clone());
+        if path.is_dir() {
+            collect_entries(&path, entries)?;
+        }
+    }
+    Ok(())
+}
+
+fn pathname(root: &Path, path: &Path) -> Result<String> {
+    let relative = path.strip_prefix(root)?;
+    let parts: Vec<&str> = relative
+        .components()
+        .map(|c| c.as_os_str().to_str().context("Non UTF-8 path"))
+        .collect::<Result<_>>()?;
+    Ok(parts.join("/"))
+}
+
+fn read_meta(path: &Path) -> Result<Option<String>> {
+    let mut meta_path = path.as_os_str().to_owned();
+    meta_path.push(".meta");
+    let meta_path = PathBuf::from(meta_path);
+    if !meta_path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(fs::read_to_string(meta_path)?))
+}
+
+fn meta_guid(meta: &str) -> Option<String> {
+    meta.lines()
+        .find_map(|line| line.strip_prefix("guid:"))
+        .map(|guid| guid.trim().to_string())
+}
+
+/// Minimal meta file with the importer Unity uses for the file type, importer settings are
+/// filled in on import.
+fn generate_meta(path: &Path) -> String {
+    let guid = Uuid::new_v4().simple().to_string();
+    let (folder, importer, main_object) = if path.is_dir() {
+        ("folderAsset: yes\n", "DefaultImporter", None)
+    } else {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        let (importer, main_object) = importer(&extension);
+        ("", importer, main_object)
+    };
+    let main_object = main_object
+        .map(|file_id| format!("  mainObjectFileID: {}\n", file_id))
+        .unwrap_or_default();
+    format!(
+        "fileFormatVersion: 2\nguid: {}\n{}{}:\n{}  externalObjects: {{}}\n  userData: \n  assetBundleName: \n  assetBundleVariant: \n",
+        guid, folder, importer, main_object
+    )
+}
+
+/// Importer of the file extension, with the main object file ID of the native Unity assets.
+fn importer(extension: &str) -> (&'static str, Option<i64>) {
+    match extension {
+        "png" | "jpg" | "jpeg" | "tga" | "tif" | "tiff" | "bmp" | "psd" | "gif" | "exr" | "hdr"
+        | "iff" | "pict" => ("TextureImporter", None),
+        "fbx" | "obj" | "dae" | "3ds" | "dxf" | "blend" | "max" | "ma" | "mb" => {
+            ("ModelImporter", None)
+        }
+        "wav" | "mp3" | "ogg" | "aif" | "aiff" | "flac" | "mod" | "it" | "s3m" | "xm" => {
+            ("AudioImporter", None)
+        }
+        "cs" => ("MonoImporter", None),
+        "shader" => ("ShaderImporter", None),
+        "txt" | "json" | "bytes" | "xml" | "csv" | "html" | "htm" | "yaml" | "fnt" => {
+            ("TextScriptImporter", None)
+        }
+        "prefab" => ("PrefabImporter", None),
+        "mat" => ("NativeFormatImporter", Some(2100000)),
+        "anim" => ("NativeFormatImporter", Some(7400000)),
+        "controller" => ("NativeFormatImporter", Some(9100000)),
+        "asset" => ("NativeFormatImporter", Some(11400000)),
+        _ => ("DefaultImporter", None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::Package;
+
+    const MATERIAL_META: &str = "fileFormatVersion: 2
+guid: bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb1
+NativeFormatImporter:
+  externalObjects: {}
+  mainObjectFileID: 2100000
+  userData: 
+  assetBundleName: 
+  assetBundleVariant: 
+";
+
+    /// Empty directory for the test in the system temporary directory.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lwa_unity_unpack_{}", name));
+        if dir.exists() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(dir: &Path, pathname: &str, content: &str) {
+        let path = dir.join(pathname);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn packs_unpacked_directory() {
+        let dir = test_dir("pack_round_trip");
+        let input = dir.join("output");
+        write(&input, "materials.json", "{}");
+        write(&input, "Assets/Rocks/Rock.prefab", "%YAML 1.1");
+        write(&input, "Assets/Rocks/Rock.prefab.glb", "glTF");
+        write(&input, "Assets/Rocks/rock.png", "png");
+        write(&input, "Assets/Rocks/Rock.fbx", "fbx");
+        write(&input, "Assets/Rocks/Rock.mat", "%YAML 1.1");
+        write(&input, "Assets/Rocks/Rock.mat.meta", MATERIAL_META);
+        let output = dir.join("rocks.unitypackage");
+        pack_directory(&input, &output).unwrap();
+
+        let package = Package::open(&output).unwrap();
+        let pathnames: Vec<&str> = package
+            .assets()
+            .iter()
+            .map(|a| a.pathname.as_str())
+            .collect();
+        assert_eq!(
+            pathnames,
+            [
+                "Assets/Rocks",
+                "Assets/Rocks/Rock.fbx",
+                "Assets/Rocks/Rock.mat",
+                "Assets/Rocks/Rock.prefab",
+                "Assets/Rocks/rock.png",
+            ]
+        );
+        let meta = |pathname: &str| {
+            let asset = package.asset_by_pathname(pathname).unwrap();
+            asset.meta.clone().unwrap()
+        };
+        assert!(meta("Assets/Rocks").folder_asset);
+        assert!(meta("Assets/Rocks/Rock.fbx").model_importer.is_some());
+        assert!(meta("Assets/Rocks/rock.png").texture_importer.is_some());
+        assert!(meta("Assets/Rocks/Rock.prefab").prefab_importer.is_some());
+        let material = meta("Assets/Rocks/Rock.mat");
+        assert_eq!(material.guid, "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb1");
+        assert_eq!(
+            material.native_format_importer.unwrap().main_object_file_id,
+            2100000
+        );
+        assert_eq!(
+            package.read_meta("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb1"),
+            Some(MATERIAL_META)
+        );
+    }
+
+    #[test]
+    fn rejects_files_outside_of_assets() {
+        let dir = test_dir("pack_outside_assets");
+        let input = dir.join("project");
+        write(&input, "Assets/rock.png", "png");
+        write(&input, "ProjectSettings/TagManager.asset", "%YAML 1.1");
+        let output = dir.join("project.unitypackage");
+        assert!(pack_directory(&input, &output).is_err());
+        assert!(!output.exists());
+    }
+}
@@ -0,0 +1,204 @@
+use crate::archive::{self, EntryKind};
+use crate::asset::{Asset, AssetType};
+use crate::filter::AssetFilter;
+use crate::unpacker::{UnpackOptions, Unpacker};
+use anyhow::{bail, Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Index of a `.unitypackage` file.
+///
+/// Opening the package reads the archive once, keeping pathnames and meta files in memory.
+/// Asset bodies are only read when requested.
+#[derive(Clone, Debug)]
+pub struct Package {
+    path: PathBuf,
+    assets: Vec<Asset>,
+    /// Positions in `assets` by GUID.
+    guids: HashMap<String, usize>,
+    metas: HashMap<String, String>,
+}
+
+impl Package {
+    pub fn open(path: impl AsRef<Path>) -> Result<Package> {
+        let path = path.as_ref();
+        if !path.exists() {
+            bail!("Input file does not exits: {}", path.display());
+        }
+        let mut entries: HashMap<String, (Option<String>, Option<u64>)> = HashMap::new();
+        let mut metas = HashMap::new();
+
+        let mut archive = archive::open(path)?;
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let entry_path = entry.path()?.into_owned();
+            let Some((guid, kind)) = archive::split_entry_path(&entry_path) else {
+                continue;
+            };
+            match kind {
+                EntryKind::Pathname => {
+                    entries.entry(guid).or_default().0 = archive::read_pathname(&mut entry)?;
+                }
+                EntryKind::Asset => {
+                    entries.entry(guid).or_default().1 = Some(entry.size());
+                }
+                EntryKind::Meta => {
+                    let mut meta = String::new();
+                    entry.read_to_string(&mut meta)?;
+                    metas.insert(guid, meta);
+                }
+                _ => continue,
+            }
+        }
+
+        let mut assets: Vec<Asset> = entries
+            .into_iter()
+            .filter_map(|(guid, (pathname, size))| {
+                let mut asset = Asset::new(guid, pathname?);
+                match size {
+                    Some(size) => asset.size = size,
+                    None => asset.asset_type = AssetType::Folder,
+                }
+                if let Some(meta) = metas.get(&asset.guid) {
+                    asset.set_meta(meta);
+                }
+                Some(asset)
+            })
+            .collect();
+        assets.sort_by(|a, b| a.pathname.split('/').cmp(b.pathname.split('/')));
+        let guids = assets
+            .iter()
+            .enumerate()
+            .map(|(i, a)| (a.guid.clone(), i))
+            .collect();
+
+        Ok(Package {
+            path: path.to_path_buf(),
+            assets,
+            guids,
+            metas,
+        })
+    }
+
+    /// Path of the `.unitypackage` file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// All entries of the package sorted by pathname, including folders.
+    pub fn assets(&self) -> &[Asset] {
+        &self.assets
+    }
+
+    pub fn asset(&self, guid: &str) -> Option<&Asset> {
+        self.guids.get(guid).map(|i| &self.assets[*i])
+    }
+
+    pub fn asset_by_pathname(&self, pathname: &str) -> Option<&Asset> {
+        self.assets.iter().find(|a| a.pathname == pathname)
+    }
+
+    /// Content of the `asset.meta` file for given GUID.
+    pub fn read_meta(&self, guid: &str) -> Option<&str> {
+        self.metas.get(guid).map(String::as_str)
+    }
+
+    /// Reads the asset body, this goes through the archive until the entry is found.
+    pub fn read_asset(&self, guid: &str) -> Result<Vec<u8>> {
+        let mut archive = archive::open(&self.path)?;
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let entry_path = entry.path()?.into_owned();
+            match archive::split_entry_path(&entry_path) {
+                Some((entry_guid, EntryKind::Asset)) if entry_guid == guid => {
+                    let mut content = Vec::with_capacity(entry.size() as usize);
+                    entry.read_to_end(&mut content)?;
+                    return Ok(content);
+                }
+                _ => continue,
+            }
+        }
+        bail!("Asset {} not found in {}", guid, self.path.display())
+    }
+
+    /// Goes through the archive once, calling `f` with every asset body matching the `filter`.
+    pub fn for_each_asset<F>(&self, filter: impl Fn(&Asset) -> bool, mut f: F) -> Result<()>
+    where
+        F: FnMut(&Asset, &mut dyn Read) -> Result<()>,
+    {
+        let index: HashMap<&str, &Asset> = self
+            .assets
+            .iter()
+            .filter(|a| filter(a))
+            .map(|a| (a.guid.as_str(), a))
+            .collect();
+        let mut archive = archive::open(&self.path)?;
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let entry_path = entry.path()?.into_owned();
+            let Some((guid, EntryKind::Asset)) = archive::split_entry_path(&entry_path) else {
+                continue;
+            };
+            if let Some(asset) = index.get(guid.as_str()) {
+                f(asset, &mut entry)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Extracts the package into `output_dir`, returning the extracted assets.
+    pub fn extract(&self, output_dir: &Path, options: UnpackOptions) -> Result<Vec<Asset>> {
+        let mut unpacker = Unpacker::new(output_dir, options);
+        unpacker
+            .unpack(&self.path)
+            .with_context(|| format!("Failed to extract {}", self.path.display()))?;
+        Ok(unpacker.assets)
+    }
+
+    /// Writes entries matching the filter into a new package at `output`.
+    ///
+    /// Entries are copied verbatim (asset, meta, preview) from one archive into another,
+    /// folders are kept when they contain any of the selected assets.
+    pub fn repack(&self, output: &Path, filter: &AssetFilter) -> Result<Vec<Asset>> {
+        let selected: Vec<&Asset> = self
+            .assets
+            .iter()
+            .filter(|a| a.asset_type != AssetType::Folder && filter.matches(a))
+            .collect();
+        let mut folders = HashSet::new();
+        for asset in &selected {
+            let mut path = asset.pathname.as_str();
+            while let Some((parent, _)) = path.rsplit_once('/') {
+                folders.insert(parent);
+                path = parent;
+            }
+        }
+        let mut assets: Vec<Asset> = self
+            .assets
+            .iter()
+            .filter(|a| a.asset_type == AssetType::Folder && folders.contains(a.pathname.as_str()))
+            .chain(selected)
+            .cloned()
+            .collect();
+        assets.sort_by(|a, b| a.pathname.split('/').cmp(b.pathname.split('/')));
+        let guids: HashSet<&str> = assets.iter().map(|a| a.guid.as_str()).collect();
+
+        let mut builder = archive::create(output)?;
+        let mut archive = archive::open(&self.path)?;
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let entry_path = entry.path()?.into_owned();
+            match archive::split_entry_path(&entry_path) {
+                Some((guid, _)) if guids.contains(guid.as_str()) => {
+                    let header = entry.header().clone();
+                    builder.append(&header, &mut entry)?;
+                }
+                _ => continue,
+            }
+        }
+        archive::finish(builder)?;
+
+        Ok(assets)
+    }
+}
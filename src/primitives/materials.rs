@@ -2,25 +2,108 @@ use crate::primitives::reference::FileReference;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 // use bevy::prelude::*;
-use crate::yaml_helpers::parse_unity_yaml;
-use anyhow::{bail, Context, Result};
+use crate::primitives::class_id::{self, UnityClass};
+use crate::yaml_helpers::{find_object, parse_unity_documents};
+use anyhow::{bail, Result};
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
 pub struct UnityMaterial {
     #[serde(alias = "m_Name")]
     pub name: String,
 
-    #[serde(alias = "m_Shader")]
+    /// Material Variants can leave it out, the parent shader is used then.
+    #[serde(default, alias = "m_Shader")]
     pub shader: FileReference,
 
-    #[serde(alias = "m_SavedProperties")]
+    #[serde(default, alias = "m_SavedProperties")]
     pub properties: SavedProperties,
 
     #[serde(default, alias = "stringTagMap")]
     pub string_tags: HashMap<String, String>,
+
+    /// Space separated keywords, replaced by `m_ValidKeywords` list since Unity 2021.2.
+    #[serde(default, alias = "m_ShaderKeywords")]
+    pub shader_keywords: Option<String>,
+
+    #[serde(default, alias = "m_ValidKeywords")]
+    pub valid_keywords: Vec<String>,
+
+    /// -1 when the shader queue is used.
+    #[serde(default = "default_render_queue", alias = "m_CustomRenderQueue")]
+    pub custom_render_queue: i32,
+
+    /// Parent of the Material Variant, variants store only the overridden properties.
+    #[serde(default, alias = "m_Parent")]
+    pub parent: FileReference,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+fn default_render_queue() -> i32 {
+    -1
+}
+
+impl UnityMaterial {
+    /// Enabled shader keywords, from both the old and the new format.
+    pub fn keywords(&self) -> impl Iterator<Item = &str> {
+        self.shader_keywords
+            .iter()
+            .flat_map(|k| k.split_whitespace())
+            .chain(self.valid_keywords.iter().map(String::as_str))
+    }
+
+    pub fn has_keyword(&self, keyword: &str) -> bool {
+        self.keywords().any(|k| k == keyword)
+    }
+
+    /// Fills the values missing from the variant with the ones of its `parent`.
+    pub fn inherit(&mut self, parent: &UnityMaterial) {
+        if self.shader.guid.is_none() {
+            self.shader = parent.shader.clone();
+        }
+        self.properties.inherit(&parent.properties);
+        for (tag, value) in &parent.string_tags {
+            self.string_tags
+                .entry(tag.clone())
+                .or_insert_with(|| value.clone());
+        }
+        if self.keywords().next().is_none() {
+            self.shader_keywords = parent.shader_keywords.clone();
+            self.valid_keywords = parent.valid_keywords.clone();
+        }
+        if self.custom_render_queue < 0 {
+            self.custom_render_queue = parent.custom_render_queue;
+        }
+    }
+}
+
+/// Merges the parent chain into every Material Variant of `materials`, keyed by GUID.
+///
+/// Parents missing from `materials` are reported, the variant keeps only its own properties then.
+pub fn resolve_variants(materials: &mut HashMap<String, UnityMaterial>) {
+    let parents = materials.clone();
+    for (guid, material) in materials.iter_mut() {
+        let mut visited = vec![guid.clone()];
+        let mut parent_guid = material.parent.guid.clone();
+        while let Some(guid) = parent_guid {
+            if visited.contains(&guid) {
+                eprintln!("Material {} has cyclic parents", material.name);
+                break;
+            }
+            let Some(parent) = parents.get(&guid) else {
+                eprintln!(
+                    "Parent {} of material {} is missing from the package",
+                    guid, material.name
+                );
+                break;
+            };
+            material.inherit(parent);
+            parent_guid = parent.parent.guid.clone();
+            visited.push(guid);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(default)]
 pub struct SavedProperties {
     #[serde(alias = "serializedVersion")]
     pub serialized_version: u64,
@@ -35,7 +118,45 @@ pub struct SavedProperties {
     pub colors: Vec<HashMap<String, UnityColor>>,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+impl SavedProperties {
+    /// Texture property with assigned texture asset.
+    pub fn texture(&self, name: &str) -> Option<&TextureInfo> {
+        self.tex_envs
+            .iter()
+            .find_map(|tex| tex.get(name))
+            .filter(|t| t.texture.guid.is_some())
+    }
+
+    pub fn float(&self, name: &str) -> Option<f32> {
+        self.floats.iter().find_map(|f| f.get(name)).copied()
+    }
+
+    pub fn color(&self, name: &str) -> Option<UnityColor> {
+        self.colors.iter().find_map(|c| c.get(name)).copied()
+    }
+
+    /// Adds the `parent` properties that are not overridden.
+    pub fn inherit(&mut self, parent: &SavedProperties) {
+        inherit_entries(&mut self.tex_envs, &parent.tex_envs);
+        inherit_entries(&mut self.floats, &parent.floats);
+        inherit_entries(&mut self.colors, &parent.colors);
+    }
+}
+
+fn inherit_entries<T: Clone>(entries: &mut Vec<HashMap<String, T>>, parent: &[HashMap<String, T>]) {
+    let inherited: Vec<HashMap<String, T>> = parent
+        .iter()
+        .filter(|entry| {
+            entry
+                .keys()
+                .all(|name| !entries.iter().any(|e| e.contains_key(name)))
+        })
+        .cloned()
+        .collect();
+    entries.splice(0..0, inherited);
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
 pub struct TextureInfo {
     #[serde(alias = "m_Texture")]
     pub texture: FileReference,
@@ -80,22 +201,122 @@ pub struct UnityColor {
 //     }
 // }
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(tag = "object_type")]
-enum MaterialContainer {
-    Material(UnityMaterial),
-    #[serde(other)]
-    DontCare,
+impl UnityClass for UnityMaterial {
+    const CLASS_ID: u32 = class_id::MATERIAL;
 }
 
 pub fn read_single_material(contents: &str) -> Result<UnityMaterial> {
-    let map = parse_unity_yaml(contents)?;
+    let parsed = parse_unity_documents(contents);
+    if let Some(material) = find_object(&parsed.documents) {
+        return material;
+    }
+    match parsed.errors.into_iter().next() {
+        Some(e) => Err(e.context("invalid material file")),
+        None if parsed.documents.is_empty() => bail!("0 items in material file"),
+        None => bail!("invalid material file"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = r#"%YAML 1.1
+%TAG !u! tag:unity3d.com,2011:
+--- !u!21 &2100000
+Material:
+  serializedVersion: 8
+  m_Name: Base
+  m_Shader: {fileID: 46, guid: 0000000000000000f000000000000000, type: 0}
+  m_ValidKeywords:
+  - _EMISSION
+  m_CustomRenderQueue: 2450
+  m_SavedProperties:
+    serializedVersion: 3
+    m_TexEnvs:
+    - _MainTex:
+        m_Texture: {fileID: 2800000, guid: aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa1, type: 3}
+        m_Scale: {x: 1, y: 1}
+        m_Offset: {x: 0, y: 0}
+    m_Floats:
+    - _Glossiness: 0.5
+    - _Metallic: 0
+    m_Colors:
+    - _Color: {r: 1, g: 1, b: 1, a: 1}
+"#;
+
+    /// Variant storing only its parent and the overridden properties.
+    const VARIANT: &str = r#"%YAML 1.1
+%TAG !u! tag:unity3d.com,2011:
+--- !u!21 &2100000
+Material:
+  serializedVersion: 8
+  m_Name: Variant
+  m_Parent: {fileID: 2100000, guid: bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb1, type: 2}
+  m_SavedProperties:
+    m_Floats:
+    - _Metallic: 1
+"#;
+
+    fn variant(name: &str, parent: &str) -> UnityMaterial {
+        let mut material = read_single_material(VARIANT).unwrap();
+        material.name = name.to_string();
+        material.parent.guid = Some(parent.to_string());
+        material
+    }
+
+    #[test]
+    fn parses_variant_without_shader() {
+        let material = read_single_material(VARIANT).unwrap();
+        assert_eq!(material.shader.guid, None);
+        assert_eq!(material.properties.float("_Metallic"), Some(1.0));
+        assert!(material.properties.tex_envs.is_empty());
+    }
+
+    #[test]
+    fn variants_inherit_parent_chain() {
+        let mut green = variant("Green", "base");
+        green.properties.floats = vec![HashMap::from([("_Glossiness".to_string(), 0.9)])];
+        let mut materials = HashMap::from([
+            ("base".to_string(), read_single_material(BASE).unwrap()),
+            ("variant".to_string(), variant("Variant", "base")),
+            ("green".to_string(), green),
+            ("dark".to_string(), variant("Dark", "green")),
+        ]);
+        resolve_variants(&mut materials);
 
-    let (_, output) = map.into_iter().next().context("0 items in material file")?;
+        let variant = &materials["variant"];
+        assert_eq!(variant.shader.file_id, 46);
+        assert_eq!(variant.properties.float("_Metallic"), Some(1.0));
+        assert_eq!(variant.properties.float("_Glossiness"), Some(0.5));
+        assert!(variant.has_keyword("_EMISSION"));
+        assert_eq!(variant.custom_render_queue, 2450);
+        let texture = variant.properties.tex_envs[0]["_MainTex"]
+            .texture
+            .guid
+            .as_deref();
+        assert_eq!(texture, Some("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa1"));
 
-    let MaterialContainer::Material(mat) = output else {
-        bail!("invalid material file");
-    };
+        // the grandparent is reached through the middle variant
+        let dark = &materials["dark"];
+        assert_eq!(dark.shader.file_id, 46);
+        assert_eq!(dark.properties.float("_Metallic"), Some(1.0));
+        assert_eq!(dark.properties.float("_Glossiness"), Some(0.9));
+        assert_eq!(dark.custom_render_queue, 2450);
+    }
 
-    Ok(mat)
+    #[test]
+    fn cyclic_variants_keep_own_properties() {
+        let mut materials = HashMap::from([
+            ("first".to_string(), variant("First", "second")),
+            ("second".to_string(), variant("Second", "first")),
+            ("orphan".to_string(), variant("Orphan", "missing")),
+        ]);
+        resolve_variants(&mut materials);
+        for material in materials.values() {
+            assert_eq!(material.shader.guid, None);
+            assert_eq!(material.properties.float("_Metallic"), Some(1.0));
+            assert_eq!(material.custom_render_queue, -1);
+        }
+    }
 }
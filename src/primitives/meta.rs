@@ -0,0 +1,689 @@
+use crate::primitives::materials::UnityVector2;
+use crate::primitives::reference::FileReference;
+use crate::yaml_helpers::quote_guids;
+use anyhow::Result;
+use serde::{
+    de::{self, DeserializeOwned},
+    Deserialize, Deserializer, Serialize,
+};
+use std::collections::HashMap;
+
+/// Content of the `.meta` file, only the most common importers are typed.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct MetaFile {
+    #[serde(default, alias = "fileFormatVersion", deserialize_with = "lenient")]
+    pub file_format_version: u32,
+    #[serde(default, deserialize_with = "lenient")]
+    pub guid: String,
+    #[serde(default, alias = "folderAsset", deserialize_with = "deserialize_bool")]
+    pub folder_asset: bool,
+
+    #[serde(
+        default,
+        alias = "TextureImporter",
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "lenient"
+    )]
+    pub texture_importer: Option<TextureImporter>,
+    #[serde(
+        default,
+        alias = "ModelImporter",
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "lenient"
+    )]
+    pub model_importer: Option<ModelImporter>,
+    #[serde(
+        default,
+        alias = "AudioImporter",
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "lenient"
+    )]
+    pub audio_importer: Option<AudioImporter>,
+    #[serde(
+        default,
+        alias = "DefaultImporter",
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "lenient"
+    )]
+    pub default_importer: Option<DefaultImporter>,
+    #[serde(
+        default,
+        alias = "NativeFormatImporter",
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "lenient"
+    )]
+    pub native_format_importer: Option<NativeFormatImporter>,
+    #[serde(
+        default,
+        alias = "PrefabImporter",
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "lenient"
+    )]
+    pub prefab_importer: Option<DefaultImporter>,
+}
+
+impl MetaFile {
+    /// Material remaps of the model, empty for other importers.
+    pub fn external_objects(&self) -> &[ExternalObject] {
+        self.model_importer
+            .as_ref()
+            .map(|i| i.external_objects.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct TextureImporter {
+    #[serde(default, alias = "serializedVersion", deserialize_with = "lenient")]
+    pub serialized_version: u32,
+    #[serde(default, deserialize_with = "lenient")]
+    pub mipmaps: MipmapSettings,
+    #[serde(default, deserialize_with = "lenient")]
+    pub bumpmap: BumpmapSettings,
+    #[serde(default, alias = "isReadable", deserialize_with = "deserialize_bool")]
+    pub is_readable: bool,
+    #[serde(default, alias = "maxTextureSize", deserialize_with = "lenient")]
+    pub max_texture_size: u32,
+    #[serde(default, alias = "textureSettings", deserialize_with = "lenient")]
+    pub texture_settings: TextureSettings,
+    /// 0 - Default, 1 - Normal map, 8 - Sprite, see `TextureImporterType`.
+    #[serde(default, alias = "textureType", deserialize_with = "lenient")]
+    pub texture_type: i32,
+    #[serde(default, alias = "textureShape", deserialize_with = "lenient")]
+    pub texture_shape: i32,
+    /// 0 - None, 1 - Single, 2 - Multiple.
+    #[serde(default, alias = "spriteMode", deserialize_with = "lenient")]
+    pub sprite_mode: i32,
+    #[serde(default, alias = "spritePixelsToUnits", deserialize_with = "lenient")]
+    pub sprite_pixels_to_units: f32,
+    #[serde(default, alias = "spritePivot", deserialize_with = "lenient")]
+    pub sprite_pivot: UnityVector2,
+    #[serde(default, alias = "spriteBorder", deserialize_with = "lenient")]
+    pub sprite_border: UnityVector4,
+    #[serde(default, alias = "alphaUsage", deserialize_with = "lenient")]
+    pub alpha_usage: i32,
+    #[serde(
+        default,
+        alias = "alphaIsTransparency",
+        deserialize_with = "deserialize_bool"
+    )]
+    pub alpha_is_transparency: bool,
+    #[serde(default, alias = "spriteSheet", deserialize_with = "lenient")]
+    pub sprite_sheet: SpriteSheet,
+}
+
+impl TextureImporter {
+    /// Color texture that should be sampled in sRGB space.
+    pub fn is_srgb(&self) -> bool {
+        self.mipmaps.srgb_texture
+    }
+
+    pub fn is_normal_map(&self) -> bool {
+        self.texture_type == 1
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct MipmapSettings {
+    #[serde(default, alias = "enableMipMap", deserialize_with = "deserialize_bool")]
+    pub enable_mip_map: bool,
+    #[serde(default, alias = "sRGBTexture", deserialize_with = "deserialize_bool")]
+    pub srgb_texture: bool,
+    #[serde(
+        default,
+        alias = "linearTexture",
+        deserialize_with = "deserialize_bool"
+    )]
+    pub linear_texture: bool,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct BumpmapSettings {
+    #[serde(
+        default,
+        alias = "convertToNormalMap",
+        deserialize_with = "deserialize_bool"
+    )]
+    pub convert_to_normal_map: bool,
+    #[serde(default, alias = "heightScale", deserialize_with = "lenient")]
+    pub height_scale: f32,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct TextureSettings {
+    /// -1 - default, 0 - Point, 1 - Bilinear, 2 - Trilinear.
+    #[serde(default, alias = "filterMode", deserialize_with = "lenient")]
+    pub filter_mode: i32,
+    #[serde(default, deserialize_with = "lenient")]
+    pub aniso: i32,
+    /// -1 - default, 0 - Repeat, 1 - Clamp, 2 - Mirror, 3 - MirrorOnce.
+    #[serde(default, alias = "wrapU", deserialize_with = "lenient")]
+    pub wrap_u: i32,
+    #[serde(default, alias = "wrapV", deserialize_with = "lenient")]
+    pub wrap_v: i32,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct SpriteSheet {
+    #[serde(default, deserialize_with = "lenient")]
+    pub sprites: Vec<SpriteMetaData>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct SpriteMetaData {
+    #[serde(default, deserialize_with = "lenient")]
+    pub name: String,
+    #[serde(default, deserialize_with = "lenient")]
+    pub rect: UnityRect,
+    #[serde(default, deserialize_with = "lenient")]
+    pub alignment: i32,
+    #[serde(default, deserialize_with = "lenient")]
+    pub pivot: UnityVector2,
+    #[serde(default, deserialize_with = "lenient")]
+    pub border: UnityVector4,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct ModelImporter {
+    #[serde(default, alias = "serializedVersion", deserialize_with = "lenient")]
+    pub serialized_version: u32,
+    /// Names of the objects inside the model by file ID, Unity 2019.3 and newer.
+    #[serde(default, alias = "internalIDToNameTable", deserialize_with = "lenient")]
+    pub internal_id_to_name_table: Vec<InternalIdName>,
+    /// Names of the objects inside the model by file ID, before Unity 2019.3.
+    #[serde(default, alias = "fileIDToRecycleName", deserialize_with = "lenient")]
+    pub file_id_to_recycle_name: HashMap<i64, String>,
+    #[serde(
+        default,
+        alias = "externalObjects",
+        deserialize_with = "deserialize_external_objects"
+    )]
+    pub external_objects: Vec<ExternalObject>,
+    #[serde(default, deserialize_with = "lenient")]
+    pub materials: ModelMaterialSettings,
+    #[serde(default, deserialize_with = "lenient")]
+    pub meshes: ModelMeshSettings,
+    #[serde(
+        default,
+        alias = "importAnimation",
+        deserialize_with = "deserialize_bool"
+    )]
+    pub import_animation: bool,
+    /// 0 - None, 1 - Legacy, 2 - Generic, 3 - Humanoid.
+    #[serde(default, alias = "animationType", deserialize_with = "lenient")]
+    pub animation_type: i32,
+}
+
+impl ModelImporter {
+    /// Names of the objects inside the model by file ID, regardless of the meta version.
+    pub fn object_names(&self) -> HashMap<i64, String> {
+        let mut names = self.file_id_to_recycle_name.clone();
+        for entry in &self.internal_id_to_name_table {
+            for file_id in entry.first.values() {
+                names.insert(*file_id, entry.second.clone());
+            }
+        }
+        names
+    }
+
+    /// GUIDs of the project materials used instead of the embedded ones, by embedded material name.
+    pub fn material_remaps(&self) -> HashMap<String, String> {
+        self.external_objects
+            .iter()
+            .filter(|o| o.first.object_type == "UnityEngine:Material")
+            .filter_map(|o| Some((o.first.name.clone(), o.second.guid.clone()?)))
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct InternalIdName {
+    /// Class ID mapped to file ID of the object.
+    pub first: HashMap<i64, i64>,
+    pub second: String,
+}
+
+/// Remap of the object embedded in the model to the project asset.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct ExternalObject {
+    pub first: SourceAssetIdentifier,
+    pub second: FileReference,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct SourceAssetIdentifier {
+    /// e.g. `UnityEngine:Material`
+    #[serde(default, rename = "type", deserialize_with = "lenient")]
+    pub object_type: String,
+    #[serde(default, deserialize_with = "lenient")]
+    pub assembly: String,
+    #[serde(default, deserialize_with = "lenient")]
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct ModelMaterialSettings {
+    /// 0 - None, 1 - Standard (Legacy), 2 - Import via MaterialDescription, Unity 2019.3 and newer.
+    #[serde(default, alias = "materialImportMode", deserialize_with = "lenient")]
+    pub material_import_mode: Option<i32>,
+    /// Replaced by `material_import_mode` in Unity 2019.3.
+    #[serde(
+        default,
+        alias = "importMaterials",
+        deserialize_with = "deserialize_bool"
+    )]
+    pub import_materials: bool,
+    #[serde(default, alias = "materialName", deserialize_with = "lenient")]
+    pub material_name: i32,
+    #[serde(default, alias = "materialSearch", deserialize_with = "lenient")]
+    pub material_search: i32,
+    #[serde(default, alias = "materialLocation", deserialize_with = "lenient")]
+    pub material_location: i32,
+}
+
+impl ModelMaterialSettings {
+    /// Materials are imported from the model, with either of the import settings.
+    pub fn imports_materials(&self) -> bool {
+        match self.material_import_mode {
+            Some(mode) => mode != 0,
+            None => self.import_materials,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ModelMeshSettings {
+    #[serde(
+        default = "default_scale",
+        alias = "globalScale",
+        deserialize_with = "lenient_scale"
+    )]
+    pub global_scale: f32,
+    #[serde(default, alias = "useFileScale", deserialize_with = "deserialize_bool")]
+    pub use_file_scale: bool,
+    #[serde(default, alias = "useFileUnits", deserialize_with = "deserialize_bool")]
+    pub use_file_units: bool,
+    #[serde(default, alias = "addColliders", deserialize_with = "deserialize_bool")]
+    pub add_colliders: bool,
+    #[serde(
+        default,
+        alias = "importBlendShapes",
+        deserialize_with = "deserialize_bool"
+    )]
+    pub import_blend_shapes: bool,
+    #[serde(
+        default,
+        alias = "swapUVChannels",
+        deserialize_with = "deserialize_bool"
+    )]
+    pub swap_uv_channels: bool,
+    #[serde(
+        default,
+        alias = "generateSecondaryUV",
+        deserialize_with = "deserialize_bool"
+    )]
+    pub generate_secondary_uv: bool,
+}
+
+impl Default for ModelMeshSettings {
+    fn default() -> Self {
+        ModelMeshSettings {
+            global_scale: default_scale(),
+            use_file_scale: true,
+            use_file_units: true,
+            add_colliders: false,
+            import_blend_shapes: true,
+            swap_uv_channels: false,
+            generate_secondary_uv: false,
+        }
+    }
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+fn lenient_scale<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    let value = serde_yaml::Value::deserialize(deserializer)?;
+    Ok(f32::deserialize(value).unwrap_or(default_scale()))
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct AudioImporter {
+    #[serde(default, alias = "serializedVersion", deserialize_with = "lenient")]
+    pub serialized_version: u32,
+    #[serde(default, alias = "defaultSettings", deserialize_with = "lenient")]
+    pub default_settings: AudioImporterSampleSettings,
+    #[serde(default, alias = "forceToMono", deserialize_with = "deserialize_bool")]
+    pub force_to_mono: bool,
+    #[serde(default, deserialize_with = "deserialize_bool")]
+    pub normalize: bool,
+    #[serde(
+        default,
+        alias = "preloadAudioData",
+        deserialize_with = "deserialize_bool"
+    )]
+    pub preload_audio_data: bool,
+    #[serde(
+        default,
+        alias = "loadInBackground",
+        deserialize_with = "deserialize_bool"
+    )]
+    pub load_in_background: bool,
+    #[serde(default, alias = "3D", deserialize_with = "deserialize_bool")]
+    pub is_3d: bool,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct AudioImporterSampleSettings {
+    /// 0 - Decompress On Load, 1 - Compressed In Memory, 2 - Streaming.
+    #[serde(default, alias = "loadType", deserialize_with = "lenient")]
+    pub load_type: i32,
+    #[serde(default, alias = "sampleRateSetting", deserialize_with = "lenient")]
+    pub sample_rate_setting: i32,
+    #[serde(default, alias = "sampleRateOverride", deserialize_with = "lenient")]
+    pub sample_rate_override: u32,
+    /// 0 - PCM, 1 - Vorbis, 2 - ADPCM.
+    #[serde(default, alias = "compressionFormat", deserialize_with = "lenient")]
+    pub compression_format: i32,
+    #[serde(default, deserialize_with = "lenient")]
+    pub quality: f32,
+}
+
+/// Used by `DefaultImporter` and `PrefabImporter`.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct DefaultImporter {
+    #[serde(
+        default,
+        alias = "externalObjects",
+        deserialize_with = "deserialize_external_objects"
+    )]
+    pub external_objects: Vec<ExternalObject>,
+    #[serde(default, alias = "userData", deserialize_with = "lenient")]
+    pub user_data: Option<String>,
+    #[serde(default, alias = "assetBundleName", deserialize_with = "lenient")]
+    pub asset_bundle_name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct NativeFormatImporter {
+    #[serde(default, alias = "mainObjectFileID", deserialize_with = "lenient")]
+    pub main_object_file_id: i64,
+    #[serde(default, alias = "userData", deserialize_with = "lenient")]
+    pub user_data: Option<String>,
+    #[serde(default, alias = "assetBundleName", deserialize_with = "lenient")]
+    pub asset_bundle_name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone)]
+pub struct UnityVector4 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone)]
+pub struct UnityRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+pub fn read_meta(contents: &str) -> Result<MetaFile> {
+    let meta = serde_yaml::from_str(&quote_guids(contents))?;
+    Ok(meta)
+}
+
+/// Values of unexpected type fall back to the default, so one odd field doesn't drop the
+/// whole meta file with the GUID remaps in it.
+fn lenient<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned + Default,
+{
+    let value = serde_yaml::Value::deserialize(deserializer)?;
+    Ok(T::deserialize(value).unwrap_or_default())
+}
+
+/// Unity writes flags as `0`/`1` and sometimes as `yes`/`no`, other values are read as `false`.
+pub(crate) fn deserialize_bool<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde_yaml::Value;
+
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Bool(value) => value,
+        Value::Number(value) => value.as_f64().is_some_and(|v| v != 0.0),
+        Value::String(value) => matches!(value.as_str(), "yes" | "true" | "1"),
+        _ => false,
+    })
+}
+
+/// Empty `externalObjects` are written as `{}` instead of an empty list.
+fn deserialize_external_objects<'de, D>(deserializer: D) -> Result<Vec<ExternalObject>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ListOrMap {
+        List(Vec<ExternalObject>),
+        Other(de::IgnoredAny),
+    }
+
+    Ok(match ListOrMap::deserialize(deserializer)? {
+        ListOrMap::List(list) => list,
+        _ => vec![],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXTURE_META: &str = "fileFormatVersion: 2
+guid: 8f6e2b7c1d0a4e5f9a3b2c1d0e9f8a7b
+TextureImporter:
+  internalIDToNameTable: []
+  externalObjects: {}
+  serializedVersion: 12
+  mipmaps:
+    mipMapMode: 0
+    enableMipMap: 1
+    sRGBTexture: 0
+    linearTexture: 0
+    fadeOut: 0
+    borderMipMap: 0
+    mipMapsPreserveCoverage: 0
+    alphaTestReferenceValue: 0.5
+    mipMapFadeDistanceStart: 1
+    mipMapFadeDistanceEnd: 3
+  bumpmap:
+    convertToNormalMap: 0
+    externalNormalMap: 0
+    heightScale: 0.25
+    normalMapFilter: 0
+  isReadable: 0
+  streamingMipmaps: 0
+  grayScaleToAlpha: 0
+  generateCubemap: 6
+  cubemapConvolution: 0
+  seamlessCubemap: 0
+  textureFormat: 1
+  maxTextureSize: 2048
+  textureSettings:
+    serializedVersion: 2
+    filterMode: 1
+    aniso: 1
+    mipBias: 0
+    wrapU: 0
+    wrapV: 0
+    wrapW: 0
+  nPOTScale: 1
+  lightmap: 0
+  compressionQuality: 50
+  spriteMode: 0
+  spriteExtrude: 1
+  spriteMeshType: 1
+  alignment: 0
+  spritePivot: {x: 0.5, y: 0.5}
+  spritePixelsToUnits: 100
+  spriteBorder: {x: 0, y: 0, z: 0, w: 0}
+  spriteGenerateFallbackPhysicsShape: 1
+  alphaUsage: 1
+  alphaIsTransparency: 0
+  spriteTessellationDetail: -1
+  textureType: 1
+  textureShape: 1
+  platformSettings:
+  - serializedVersion: 3
+    buildTarget: DefaultTexturePlatform
+    maxTextureSize: 2048
+    textureCompression: 1
+  spriteSheet:
+    serializedVersion: 2
+    sprites: []
+    outline: []
+    physicsShape: []
+    bones: []
+    spriteID:
+    internalID: 0
+    vertices: []
+    indices:
+    edges: []
+    weights: []
+    secondaryTextures: []
+  spritePackingTag:
+  pSDRemoveMatte: 0
+  userData:
+  assetBundleName:
+  assetBundleVariant:
+";
+
+    const MODEL_META: &str = "fileFormatVersion: 2
+guid: 3c1d0e9f8a7b8f6e2b7c1d0a4e5f9a3b
+ModelImporter:
+  serializedVersion: 21202
+  internalIDToNameTable:
+  - first:
+      1: 100000
+    second: Rock_01
+  - first:
+      43: 4300000
+    second: Rock_01
+  externalObjects:
+  - first:
+      type: UnityEngine:Material
+      assembly: UnityEngine.CoreModule
+      name: lambert1
+    second: {fileID: 2100000, guid: 0e9f8a7b8f6e2b7c1d0a4e5f9a3b3c1d, type: 2}
+  materials:
+    materialImportMode: 2
+    materialName: 0
+    materialSearch: 1
+    materialLocation: 1
+  animations:
+    legacyGenerateAnimations: 4
+    bakeSimulation: 0
+    clipAnimations: []
+    isReadable: 0
+  meshes:
+    lODScreenPercentages: []
+    globalScale: 0.01
+    meshCompression: 0
+    addColliders: 0
+    useSRGBMaterialColor: 1
+    importBlendShapes: 1
+    swapUVChannels: 0
+    generateSecondaryUV: 0
+    useFileUnits: 1
+    useFileScale: 1
+  importAnimation: 1
+  animationType: 2
+  userData:
+  assetBundleName:
+  assetBundleVariant:
+";
+
+    const LEGACY_MODEL_META: &str = "fileFormatVersion: 2
+guid: 7b8f6e2b7c1d0a4e5f9a3b3c1d0e9f8a
+timeCreated: 1528286342
+ModelImporter:
+  serializedVersion: 22
+  fileIDToRecycleName:
+    100000: Rock_02
+    2300000: Rock_02
+    3300000: Rock_02
+    4300000: Rock_02
+  externalObjects: {}
+  materials:
+    importMaterials: 1
+    materialName: 0
+    materialSearch: 1
+    materialLocation: 0
+  meshes:
+    globalScale: 1
+    useFileScale: 1
+  importAnimation: yes
+  animationType: 0
+";
+
+    #[test]
+    fn texture_importer() {
+        let meta = read_meta(TEXTURE_META).unwrap();
+        assert_eq!(meta.guid, "8f6e2b7c1d0a4e5f9a3b2c1d0e9f8a7b");
+        let importer = meta.texture_importer.unwrap();
+        assert!(importer.is_normal_map());
+        assert!(!importer.is_srgb());
+        assert!(importer.mipmaps.enable_mip_map);
+        assert_eq!(importer.bumpmap.height_scale, 0.25);
+        assert_eq!(importer.max_texture_size, 2048);
+        assert_eq!(importer.texture_settings.filter_mode, 1);
+        assert_eq!(importer.sprite_pivot.x, 0.5);
+    }
+
+    #[test]
+    fn model_importer() {
+        let meta = read_meta(MODEL_META).unwrap();
+        let importer = meta.model_importer.unwrap();
+        assert_eq!(importer.materials.material_import_mode, Some(2));
+        assert!(importer.materials.imports_materials());
+        assert_eq!(importer.meshes.global_scale, 0.01);
+        assert_eq!(importer.animation_type, 2);
+        assert_eq!(importer.object_names()[&4300000], "Rock_01");
+        assert_eq!(
+            importer.material_remaps()["lambert1"],
+            "0e9f8a7b8f6e2b7c1d0a4e5f9a3b3c1d"
+        );
+    }
+
+    #[test]
+    fn legacy_model_importer() {
+        let meta = read_meta(LEGACY_MODEL_META).unwrap();
+        let importer = meta.model_importer.unwrap();
+        assert_eq!(importer.materials.material_import_mode, None);
+        assert!(importer.materials.import_materials);
+        assert!(importer.materials.imports_materials());
+        assert!(importer.import_animation);
+        assert_eq!(importer.object_names()[&3300000], "Rock_02");
+        assert!(importer.external_objects.is_empty());
+    }
+
+    #[test]
+    fn odd_fields_keep_remaps() {
+        let content = MODEL_META
+            .replace("globalScale: 0.01", "globalScale: {x: 1}")
+            .replace("materialImportMode: 2", "materialImportMode: [2]")
+            .replace("animationType: 2", "animationType: Generic")
+            .replace("importAnimation: 1", "importAnimation: 0.5");
+        let importer = read_meta(&content).unwrap().model_importer.unwrap();
+        assert_eq!(importer.meshes.global_scale, 1.0);
+        assert_eq!(importer.materials.material_import_mode, None);
+        assert_eq!(importer.animation_type, 0);
+        assert!(importer.import_animation);
+        assert_eq!(importer.material_remaps().len(), 1);
+    }
+}
@@ -1,2 +1,5 @@
+pub mod class_id;
 pub mod materials;
+pub mod meta;
+pub mod prefab;
 pub mod reference;
@@ -0,0 +1,841 @@
+use crate::primitives::class_id::{self, UnityClass};
+use crate::primitives::materials::UnityVector2;
+use crate::primitives::meta::deserialize_bool;
+use crate::primitives::reference::FileReference;
+use crate::yaml_helpers::{parse_unity_documents, UnityDocument};
+use anyhow::Result;
+use serde::{de, Deserialize, Deserializer, Serialize};
+use serde_yaml::Value;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, PartialEq)]
+pub struct UnityVector3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct UnityQuaternion {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Default for UnityQuaternion {
+    fn default() -> Self {
+        UnityQuaternion {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            w: 1.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GameObject {
+    #[serde(default, alias = "m_Name")]
+    pub name: String,
+    #[serde(default, alias = "m_Component")]
+    pub components: Vec<ComponentReference>,
+    #[serde(default, alias = "m_Layer")]
+    pub layer: u32,
+    #[serde(default, alias = "m_TagString")]
+    pub tag: String,
+    #[serde(
+        default = "enabled",
+        alias = "m_IsActive",
+        deserialize_with = "deserialize_bool"
+    )]
+    pub is_active: bool,
+}
+
+/// Entry of `m_Component`, files older than Unity 5.5 key it by the class ID (`- 4: {fileID: 1}`).
+#[derive(Serialize, Debug, Clone)]
+pub struct ComponentReference {
+    pub component: FileReference,
+}
+
+impl<'de> Deserialize<'de> for ComponentReference {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let entry = serde_yaml::Mapping::deserialize(deserializer)?;
+        let Some((_, component)) = entry.into_iter().next() else {
+            return Err(de::Error::custom("empty component entry"));
+        };
+        let component = serde_yaml::from_value(component).map_err(de::Error::custom)?;
+        Ok(ComponentReference { component })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Transform {
+    #[serde(default, alias = "m_GameObject")]
+    pub game_object: FileReference,
+    #[serde(default, alias = "m_LocalRotation")]
+    pub local_rotation: UnityQuaternion,
+    #[serde(default, alias = "m_LocalPosition")]
+    pub local_position: UnityVector3,
+    #[serde(default = "unit_scale", alias = "m_LocalScale")]
+    pub local_scale: UnityVector3,
+    #[serde(default, alias = "m_Children")]
+    pub children: Vec<FileReference>,
+    #[serde(default, alias = "m_Father")]
+    pub father: FileReference,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform {
+            game_object: FileReference::default(),
+            local_rotation: UnityQuaternion::default(),
+            local_position: UnityVector3::default(),
+            local_scale: unit_scale(),
+            children: vec![],
+            father: FileReference::default(),
+        }
+    }
+}
+
+/// Transform of the UI elements.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct RectTransform {
+    #[serde(flatten)]
+    pub transform: Transform,
+    #[serde(default, alias = "m_AnchorMin")]
+    pub anchor_min: UnityVector2,
+    #[serde(default, alias = "m_AnchorMax")]
+    pub anchor_max: UnityVector2,
+    #[serde(default, alias = "m_AnchoredPosition")]
+    pub anchored_position: UnityVector2,
+    #[serde(default, alias = "m_SizeDelta")]
+    pub size_delta: UnityVector2,
+    #[serde(default, alias = "m_Pivot")]
+    pub pivot: UnityVector2,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct MeshFilter {
+    #[serde(default, alias = "m_GameObject")]
+    pub game_object: FileReference,
+    #[serde(default, alias = "m_Mesh")]
+    pub mesh: FileReference,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MeshRenderer {
+    #[serde(default, alias = "m_GameObject")]
+    pub game_object: FileReference,
+    #[serde(
+        default = "enabled",
+        alias = "m_Enabled",
+        deserialize_with = "deserialize_bool"
+    )]
+    pub enabled: bool,
+    /// Materials in submesh order.
+    #[serde(default, alias = "m_Materials")]
+    pub materials: Vec<FileReference>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SkinnedMeshRenderer {
+    #[serde(default, alias = "m_GameObject")]
+    pub game_object: FileReference,
+    #[serde(
+        default = "enabled",
+        alias = "m_Enabled",
+        deserialize_with = "deserialize_bool"
+    )]
+    pub enabled: bool,
+    #[serde(default, alias = "m_Materials")]
+    pub materials: Vec<FileReference>,
+    #[serde(default, alias = "m_Mesh")]
+    pub mesh: FileReference,
+    #[serde(default, alias = "m_Bones")]
+    pub bones: Vec<FileReference>,
+    #[serde(default, alias = "m_RootBone")]
+    pub root_bone: FileReference,
+}
+
+/// Script component, fields of the script are kept as YAML values.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MonoBehaviour {
+    #[serde(default, alias = "m_GameObject")]
+    pub game_object: FileReference,
+    #[serde(
+        default = "enabled",
+        alias = "m_Enabled",
+        deserialize_with = "deserialize_bool"
+    )]
+    pub enabled: bool,
+    #[serde(default, alias = "m_Script")]
+    pub script: FileReference,
+    #[serde(flatten)]
+    pub fields: BTreeMap<String, Value>,
+}
+
+/// Nested prefab with the changes made to it.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct PrefabInstance {
+    #[serde(default, alias = "m_Modification")]
+    pub modification: PrefabModification,
+    #[serde(default, alias = "m_SourcePrefab")]
+    pub source_prefab: FileReference,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct PrefabModification {
+    /// Transform the instance is attached to, file ID 0 for the root of the prefab.
+    #[serde(default, alias = "m_TransformParent")]
+    pub transform_parent: FileReference,
+    #[serde(default, alias = "m_Modifications")]
+    pub modifications: Vec<PropertyModification>,
+    #[serde(default, alias = "m_RemovedComponents")]
+    pub removed_components: Vec<FileReference>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PropertyModification {
+    /// Object of the source prefab that is modified.
+    pub target: FileReference,
+    #[serde(alias = "propertyPath")]
+    pub property_path: String,
+    #[serde(default)]
+    pub value: Value,
+    #[serde(default, alias = "objectReference")]
+    pub object_reference: FileReference,
+}
+
+/// Placeholder of the nested prefab object, used by the references from the outer prefab.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct StrippedObject {
+    #[serde(skip)]
+    pub class_id: u32,
+    #[serde(default, alias = "m_CorrespondingSourceObject")]
+    pub corresponding_source_object: FileReference,
+    #[serde(default, alias = "m_PrefabInstance")]
+    pub prefab_instance: FileReference,
+}
+
+impl UnityClass for GameObject {
+    const CLASS_ID: u32 = class_id::GAME_OBJECT;
+}
+
+impl UnityClass for Transform {
+    const CLASS_ID: u32 = class_id::TRANSFORM;
+}
+
+impl UnityClass for RectTransform {
+    const CLASS_ID: u32 = class_id::RECT_TRANSFORM;
+}
+
+impl UnityClass for MeshFilter {
+    const CLASS_ID: u32 = class_id::MESH_FILTER;
+}
+
+impl UnityClass for MeshRenderer {
+    const CLASS_ID: u32 = class_id::MESH_RENDERER;
+}
+
+impl UnityClass for SkinnedMeshRenderer {
+    const CLASS_ID: u32 = class_id::SKINNED_MESH_RENDERER;
+}
+
+impl UnityClass for MonoBehaviour {
+    const CLASS_ID: u32 = class_id::MONO_BEHAVIOUR;
+}
+
+impl UnityClass for PrefabInstance {
+    const CLASS_ID: u32 = class_id::PREFAB_INSTANCE;
+}
+
+/// Component attached to a GameObject.
+#[derive(Serialize, Debug, Clone)]
+pub enum Component {
+    Transform(Transform),
+    RectTransform(RectTransform),
+    MeshFilter(MeshFilter),
+    MeshRenderer(MeshRenderer),
+    SkinnedMeshRenderer(SkinnedMeshRenderer),
+    MonoBehaviour(MonoBehaviour),
+    /// Component without typed model, e.g. `BoxCollider`.
+    Other {
+        class_id: u32,
+        type_name: String,
+        game_object: FileReference,
+    },
+}
+
+impl Component {
+    pub fn game_object(&self) -> &FileReference {
+        match self {
+            Component::Transform(c) => &c.game_object,
+            Component::RectTransform(c) => &c.transform.game_object,
+            Component::MeshFilter(c) => &c.game_object,
+            Component::MeshRenderer(c) => &c.game_object,
+            Component::SkinnedMeshRenderer(c) => &c.game_object,
+            Component::MonoBehaviour(c) => &c.game_object,
+            Component::Other { game_object, .. } => game_object,
+        }
+    }
+
+    /// Transform or RectTransform part of the component.
+    pub fn as_transform(&self) -> Option<&Transform> {
+        match self {
+            Component::Transform(transform) => Some(transform),
+            Component::RectTransform(rect) => Some(&rect.transform),
+            _ => None,
+        }
+    }
+}
+
+/// Objects of a prefab or scene file by file ID.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct Prefab {
+    pub game_objects: BTreeMap<i64, GameObject>,
+    pub components: BTreeMap<i64, Component>,
+    pub prefab_instances: BTreeMap<i64, PrefabInstance>,
+    pub stripped: BTreeMap<i64, StrippedObject>,
+}
+
+/// GameObject or nested prefab with its children, resolved from the transforms.
+#[derive(Serialize, Debug, Clone)]
+pub struct PrefabNode {
+    /// File ID of the GameObject, or of the PrefabInstance for nested prefabs.
+    pub file_id: i64,
+    pub name: String,
+    /// GUID of the source prefab of the nested prefab.
+    pub source_prefab: Option<String>,
+    /// File ID of the Transform, `None` for nested prefabs.
+    pub transform: Option<i64>,
+    /// File IDs of the components, empty for nested prefabs.
+    pub components: Vec<i64>,
+    /// Object of the nested prefab the node is added under, `m_CorrespondingSourceObject` of the
+    /// stripped parent transform. `None` when the parent belongs to this file.
+    pub source_parent: Option<i64>,
+    pub children: Vec<PrefabNode>,
+}
+
+impl Prefab {
+    /// Objects that fail to parse are skipped with a warning, the rest of the file is kept.
+    pub fn parse(content: &str) -> Prefab {
+        let mut prefab = Prefab::default();
+        let parsed = parse_unity_documents(content);
+        for e in parsed.errors {
+            eprintln!("Skipping prefab object: {:#}", e);
+        }
+        for document in parsed.documents {
+            if let Err(e) = prefab.insert(&document) {
+                eprintln!("Skipping prefab object: {:#}", e);
+            }
+        }
+        prefab
+    }
+
+    fn insert(&mut self, document: &UnityDocument) -> Result<()> {
+        let file_id = document.file_id;
+        if document.stripped {
+            let mut stripped: StrippedObject = document.deserialize()?;
+            stripped.class_id = document.class_id;
+            self.stripped.insert(file_id, stripped);
+            return Ok(());
+        }
+        let component = match document.class_id {
+            class_id::GAME_OBJECT => {
+                self.game_objects.insert(file_id, document.deserialize()?);
+                return Ok(());
+            }
+            // prefabs older than Unity 2018.3 have a `Prefab` object with the same class ID
+            class_id::PREFAB_INSTANCE if document.get("m_SourcePrefab").is_some() => {
+                self.prefab_instances
+                    .insert(file_id, document.deserialize()?);
+                return Ok(());
+            }
+            class_id::PREFAB_INSTANCE => return Ok(()),
+            class_id::TRANSFORM => Component::Transform(document.deserialize()?),
+            class_id::RECT_TRANSFORM => Component::RectTransform(document.deserialize()?),
+            class_id::MESH_FILTER => Component::MeshFilter(document.deserialize()?),
+            class_id::MESH_RENDERER => Component::MeshRenderer(document.deserialize()?),
+            class_id::SKINNED_MESH_RENDERER => {
+                Component::SkinnedMeshRenderer(document.deserialize()?)
+            }
+            class_id::MONO_BEHAVIOUR => Component::MonoBehaviour(document.deserialize()?),
+            _ => {
+                // scenes contain settings objects that are not attached to any GameObject
+                let Some(game_object) = document.get("m_GameObject") else {
+                    return Ok(());
+                };
+                Component::Other {
+                    class_id: document.class_id,
+                    type_name: document.type_name.clone(),
+                    game_object: serde_yaml::from_value(game_object.clone())?,
+                }
+            }
+        };
+        self.components.insert(file_id, component);
+        Ok(())
+    }
+
+    /// Components of the GameObject, in the inspector order.
+    pub fn components_of(&self, game_object: i64) -> impl Iterator<Item = (i64, &Component)> {
+        let components = self
+            .game_objects
+            .get(&game_object)
+            .map(|o| o.components.as_slice())
+            .unwrap_or_default();
+        components.iter().filter_map(|c| {
+            let file_id = c.component.file_id;
+            self.components.get(&file_id).map(|c| (file_id, c))
+        })
+    }
+
+    /// Transform or RectTransform of the GameObject with its file ID.
+    pub fn transform_of(&self, game_object: i64) -> Option<(i64, &Transform)> {
+        self.components_of(game_object)
+            .find_map(|(file_id, c)| Some((file_id, c.as_transform()?)))
+    }
+
+    /// GameObject owning the component.
+    pub fn game_object_of(&self, component: i64) -> Option<(i64, &GameObject)> {
+        let file_id = self.components.get(&component)?.game_object().file_id;
+        self.game_objects.get(&file_id).map(|o| (file_id, o))
+    }
+
+    /// Hierarchy of the GameObjects and nested prefabs, the prefab root is the only root node.
+    ///
+    /// Scenes have many roots. Objects added under the nested prefab are children of its node.
+    pub fn tree(&self) -> Vec<PrefabNode> {
+        let mut children: HashMap<Option<Slot>, Vec<Slot>> = HashMap::new();
+        for (file_id, component) in &self.components {
+            if let Some(transform) = component.as_transform() {
+                children
+                    .entry(self.parent_slot(*file_id, transform.father.file_id))
+                    .or_default()
+                    .push(Slot::Transform(*file_id));
+            }
+        }
+        for (file_id, instance) in &self.prefab_instances {
+            children
+                .entry(self.parent_slot(*file_id, instance.modification.transform_parent.file_id))
+                .or_default()
+                .push(Slot::Instance(*file_id));
+        }
+        // keep the order of `m_Children`
+        for (parent, slots) in children.iter_mut() {
+            let Some(Slot::Transform(parent)) = parent else {
+                continue;
+            };
+            let Some(order) = self
+                .components
+                .get(parent)
+                .and_then(Component::as_transform)
+            else {
+                continue;
+            };
+            slots.sort_by_key(|slot| {
+                order
+                    .children
+                    .iter()
+                    .position(|child| self.slot(child.file_id) == Some(*slot))
+                    .unwrap_or(usize::MAX)
+            });
+        }
+        children
+            .get(&None)
+            .into_iter()
+            .flatten()
+            .filter_map(|slot| self.node(*slot, &children))
+            .collect()
+    }
+
+    /// File ID of the source prefab Transform used as the root of the nested prefab.
+    ///
+    /// It is the stripped transform attached to the instance parent, Unity always stores the
+    /// position of the instance root, so its target is used when there is no stripped transform.
+    pub fn instance_root_source(&self, instance: i64) -> Option<i64> {
+        let prefab_instance = self.prefab_instances.get(&instance)?;
+        let parent = self
+            .components
+            .get(&prefab_instance.modification.transform_parent.file_id)
+            .and_then(Component::as_transform);
+        let stripped: Vec<(&i64, &StrippedObject)> = self
+            .stripped
+            .iter()
+            .filter(|(_, s)| s.prefab_instance.file_id == instance)
+            .filter(|(_, s)| matches!(s.class_id, class_id::TRANSFORM | class_id::RECT_TRANSFORM))
+            .collect();
+        stripped
+            .iter()
+            .find(|(file_id, _)| {
+                parent.is_some_and(|p| p.children.iter().any(|c| c.file_id == **file_id))
+            })
+            .or(stripped.first())
+            .map(|(_, s)| s.corresponding_source_object.file_id)
+            .or_else(|| {
+                prefab_instance
+                    .modification
+                    .modifications
+                    .iter()
+                    .find(|m| m.property_path.starts_with("m_LocalPosition"))
+                    .map(|m| m.target.file_id)
+            })
+    }
+
+    /// Tree slot of the transform, stripped transforms belong to their nested prefab.
+    fn slot(&self, transform: i64) -> Option<Slot> {
+        match transform {
+            0 => None,
+            file_id => Some(match self.stripped.get(&file_id) {
+                Some(stripped) => Slot::Instance(stripped.prefab_instance.file_id),
+                None => Slot::Transform(file_id),
+            }),
+        }
+    }
+
+    /// Slot of the `father` transform, objects with parents missing from the file are reported
+    /// and attached as roots.
+    fn parent_slot(&self, file_id: i64, father: i64) -> Option<Slot> {
+        let slot = self.slot(father)?;
+        let exists = match slot {
+            Slot::Transform(transform) => self
+                .components
+                .get(&transform)
+                .and_then(Component::as_transform)
+                .is_some(),
+            Slot::Instance(instance) => self.prefab_instances.contains_key(&instance),
+        };
+        if !exists {
+            eprintln!(
+                "Parent {} of object {} is missing, using it as a root",
+                father, file_id
+            );
+            return None;
+        }
+        Some(slot)
+    }
+
+    fn source_parent(&self, father: i64) -> Option<i64> {
+        self.stripped
+            .get(&father)
+            .map(|stripped| stripped.corresponding_source_object.file_id)
+    }
+
+    fn node(&self, slot: Slot, children: &HashMap<Option<Slot>, Vec<Slot>>) -> Option<PrefabNode> {
+        let child_nodes = children
+            .get(&Some(slot))
+            .into_iter()
+            .flatten()
+            .filter_map(|child| self.node(*child, children))
+            .collect();
+        match slot {
+            Slot::Transform(transform) => {
+                let (game_object_id, game_object) = self.game_object_of(transform)?;
+                let father = self
+                    .components
+                    .get(&transform)?
+                    .as_transform()?
+                    .father
+                    .file_id;
+                Some(PrefabNode {
+                    file_id: game_object_id,
+                    name: game_object.name.clone(),
+                    source_prefab: None,
+                    transform: Some(transform),
+                    components: self
+                        .components_of(game_object_id)
+                        .map(|(id, _)| id)
+                        .collect(),
+                    source_parent: self.source_parent(father),
+                    children: child_nodes,
+                })
+            }
+            Slot::Instance(file_id) => {
+                let instance = self.prefab_instances.get(&file_id)?;
+                Some(PrefabNode {
+                    file_id,
+                    name: instance.name().unwrap_or_default().to_string(),
+                    source_prefab: instance.source_prefab.guid.clone(),
+                    transform: None,
+                    components: vec![],
+                    source_parent: self
+                        .source_parent(instance.modification.transform_parent.file_id),
+                    children: child_nodes,
+                })
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Slot {
+    Transform(i64),
+    Instance(i64),
+}
+
+impl PrefabInstance {
+    /// Modifications of the source prefab object with given file ID.
+    pub fn modifications_of(&self, target: i64) -> impl Iterator<Item = &PropertyModification> {
+        self.modification
+            .modifications
+            .iter()
+            .filter(move |m| m.target.file_id == target)
+    }
+
+    /// Name of the instance root, set by `m_Name` modification.
+    pub fn name(&self) -> Option<&str> {
+        self.modification
+            .modifications
+            .iter()
+            .find(|m| m.property_path == "m_Name")
+            .and_then(|m| m.value.as_str())
+    }
+}
+
+fn enabled() -> bool {
+    true
+}
+
+fn unit_scale() -> UnityVector3 {
+    UnityVector3 {
+        x: 1.0,
+        y: 1.0,
+        z: 1.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Prefab saved by Unity 5.4, before the nested prefabs.
+    const LEGACY_PREFAB: &str = "%YAML 1.1
+%TAG !u! tag:unity3d.com,2011:
+--- !u!1001 &100100000
+Prefab:
+  m_ObjectHideFlags: 1
+  serializedVersion: 2
+  m_Modification:
+    m_TransformParent: {fileID: 0}
+    m_Modifications: []
+    m_RemovedComponents: []
+  m_ParentPrefab: {fileID: 0}
+  m_RootGameObject: {fileID: 1000013912345678}
+  m_IsPrefabParent: 1
+--- !u!1 &1000013912345678
+GameObject:
+  m_ObjectHideFlags: 0
+  m_PrefabParentObject: {fileID: 0}
+  m_PrefabInternal: {fileID: 100100000}
+  serializedVersion: 4
+  m_Component:
+  - 4: {fileID: 4000013912345678}
+  - 33: {fileID: 33000013912345678}
+  - 23: {fileID: 23000013912345678}
+  m_Layer: 0
+  m_Name: Barrel
+  m_TagString: Untagged
+  m_IsActive: 1
+--- !u!4 &4000013912345678
+Transform:
+  m_ObjectHideFlags: 1
+  m_PrefabParentObject: {fileID: 0}
+  m_PrefabInternal: {fileID: 100100000}
+  m_GameObject: {fileID: 1000013912345678}
+  m_LocalRotation: {x: 0, y: 0, z: 0, w: 1}
+  m_LocalPosition: {x: 0, y: 0, z: 0}
+  m_LocalScale: {x: 1, y: 1, z: 1}
+  m_Children: []
+  m_Father: {fileID: 0}
+  m_RootOrder: 0
+--- !u!23 &23000013912345678
+MeshRenderer:
+  m_ObjectHideFlags: 1
+  m_PrefabParentObject: {fileID: 0}
+  m_PrefabInternal: {fileID: 100100000}
+  m_GameObject: {fileID: 1000013912345678}
+  m_Enabled: 1
+  m_CastShadows: 1
+  m_ReceiveShadows: 1
+  m_Materials:
+  - {fileID: 2100000, guid: 0c4b2f3a9d8e7f6a5b4c3d2e1f0a9b8c, type: 2}
+--- !u!33 &33000013912345678
+MeshFilter:
+  m_ObjectHideFlags: 1
+  m_PrefabParentObject: {fileID: 0}
+  m_PrefabInternal: {fileID: 100100000}
+  m_GameObject: {fileID: 1000013912345678}
+  m_Mesh: {fileID: 4300000, guid: 5e8d7c6b5a4f3e2d1c0b9a8f7e6d5c4b, type: 3}
+";
+
+    /// Prefab saved by Unity 2021.3 with a nested prefab and an object added under its child.
+    const NESTED_PREFAB: &str = "%YAML 1.1
+%TAG !u! tag:unity3d.com,2011:
+--- !u!1 &2843918273645102937
+GameObject:
+  m_ObjectHideFlags: 0
+  m_CorrespondingSourceObject: {fileID: 0}
+  m_PrefabInstance: {fileID: 0}
+  m_PrefabAsset: {fileID: 0}
+  serializedVersion: 6
+  m_Component:
+  - component: {fileID: 2843918273645102938}
+  m_Layer: 0
+  m_Name: Camp
+  m_TagString: Untagged
+  m_NavMeshLayer: 0
+  m_StaticEditorFlags: 0
+  m_IsActive: 1
+--- !u!4 &2843918273645102938
+Transform:
+  m_ObjectHideFlags: 0
+  m_CorrespondingSourceObject: {fileID: 0}
+  m_PrefabInstance: {fileID: 0}
+  m_PrefabAsset: {fileID: 0}
+  m_GameObject: {fileID: 2843918273645102937}
+  m_LocalRotation: {x: 0, y: 0, z: 0, w: 1}
+  m_LocalPosition: {x: 0, y: 0, z: 0}
+  m_LocalScale: {x: 1, y: 1, z: 1}
+  m_ConstrainProportionsScale: 0
+  m_Children:
+  - {fileID: 7384659201837465821}
+  m_Father: {fileID: 0}
+  m_RootOrder: 0
+  m_LocalEulerAnglesHint: {x: 0, y: 0, z: 0}
+--- !u!1001 &7384659201837465820
+PrefabInstance:
+  m_ObjectHideFlags: 0
+  serializedVersion: 2
+  m_Modification:
+    m_TransformParent: {fileID: 2843918273645102938}
+    m_Modifications:
+    - target: {fileID: 3456789012345678901, guid: 9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d, type: 3}
+      propertyPath: m_Name
+      value: Tent
+      objectReference: {fileID: 0}
+    - target: {fileID: 3456789012345678902, guid: 9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d, type: 3}
+      propertyPath: m_LocalPosition.x
+      value: 2.5
+      objectReference: {fileID: 0}
+    m_RemovedComponents: []
+  m_SourcePrefab: {fileID: 100100000, guid: 9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d, type: 3}
+--- !u!4 &7384659201837465821 stripped
+Transform:
+  m_CorrespondingSourceObject: {fileID: 3456789012345678902, guid: 9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d, type: 3}
+  m_PrefabInstance: {fileID: 7384659201837465820}
+  m_PrefabAsset: {fileID: 0}
+--- !u!4 &7384659201837465822 stripped
+Transform:
+  m_CorrespondingSourceObject: {fileID: 3456789012345678903, guid: 9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d, type: 3}
+  m_PrefabInstance: {fileID: 7384659201837465820}
+  m_PrefabAsset: {fileID: 0}
+--- !u!1 &1122334455667788990
+GameObject:
+  m_ObjectHideFlags: 0
+  m_CorrespondingSourceObject: {fileID: 0}
+  m_PrefabInstance: {fileID: 0}
+  m_PrefabAsset: {fileID: 0}
+  serializedVersion: 6
+  m_Component:
+  - component: {fileID: 1122334455667788991}
+  m_Layer: 0
+  m_Name: Lantern
+  m_TagString: Untagged
+  m_IsActive: 0
+--- !u!4 &1122334455667788991
+Transform:
+  m_ObjectHideFlags: 0
+  m_CorrespondingSourceObject: {fileID: 0}
+  m_PrefabInstance: {fileID: 0}
+  m_PrefabAsset: {fileID: 0}
+  m_GameObject: {fileID: 1122334455667788990}
+  m_LocalRotation: {x: 0, y: 0, z: 0, w: 1}
+  m_LocalPosition: {x: 0, y: 1, z: 0}
+  m_LocalScale: {x: 1, y: 1, z: 1}
+  m_Children: []
+  m_Father: {fileID: 7384659201837465822}
+  m_RootOrder: 0
+";
+
+    #[test]
+    fn reads_legacy_component_entries() {
+        let prefab = Prefab::parse(LEGACY_PREFAB);
+        let components: Vec<i64> = prefab
+            .components_of(1000013912345678)
+            .map(|(file_id, _)| file_id)
+            .collect();
+        assert_eq!(
+            components,
+            [4000013912345678, 33000013912345678, 23000013912345678]
+        );
+        assert_eq!(
+            prefab.transform_of(1000013912345678).map(|(id, _)| id),
+            Some(4000013912345678)
+        );
+    }
+
+    #[test]
+    fn skips_legacy_prefab_object() {
+        let prefab = Prefab::parse(LEGACY_PREFAB);
+        assert!(prefab.prefab_instances.is_empty());
+        let tree = prefab.tree();
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].name, "Barrel");
+    }
+
+    #[test]
+    fn builds_nested_hierarchy() {
+        let prefab = Prefab::parse(NESTED_PREFAB);
+        let tree = prefab.tree();
+        assert_eq!(tree.len(), 1);
+        let camp = &tree[0];
+        assert_eq!(camp.name, "Camp");
+        assert_eq!(camp.children.len(), 1);
+        let tent = &camp.children[0];
+        assert_eq!(tent.name, "Tent");
+        assert_eq!(tent.file_id, 7384659201837465820);
+        assert_eq!(
+            tent.source_prefab.as_deref(),
+            Some("9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d")
+        );
+        assert_eq!(
+            prefab.instance_root_source(7384659201837465820),
+            Some(3456789012345678902)
+        );
+        let names: Vec<&str> = tent.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["Lantern"]);
+        assert_eq!(tent.source_parent, None);
+        assert_eq!(tent.children[0].source_parent, Some(3456789012345678903));
+        assert!(!prefab.game_objects[&1122334455667788990].is_active);
+    }
+
+    #[test]
+    fn skips_malformed_objects() {
+        let content = NESTED_PREFAB.replace(
+            "  m_LocalPosition: {x: 0, y: 1, z: 0}",
+            "  m_LocalPosition: [broken",
+        );
+        let prefab = Prefab::parse(&content);
+        assert!(prefab.game_objects.contains_key(&2843918273645102937));
+        assert!(!prefab.components.contains_key(&1122334455667788991));
+        assert_eq!(prefab.tree()[0].children[0].children.len(), 0);
+    }
+
+    #[test]
+    fn keeps_objects_with_missing_father() {
+        let content = NESTED_PREFAB.replace(
+            "  m_Father: {fileID: 7384659201837465822}",
+            "  m_Father: {fileID: 42}",
+        );
+        let prefab = Prefab::parse(&content);
+        let tree = prefab.tree();
+        assert_eq!(tree.len(), 2);
+        let lantern = tree.iter().find(|node| node.name == "Lantern").unwrap();
+        assert_eq!(lantern.transform, Some(1122334455667788991));
+        assert_eq!(lantern.source_parent, None);
+        assert!(tree
+            .iter()
+            .flat_map(|node| &node.children)
+            .all(|child| child.name != "Lantern"));
+    }
+}
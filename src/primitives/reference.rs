@@ -5,7 +5,7 @@ use serde::{
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct FileReference {
-    #[serde(alias = "fileID")]
+    #[serde(alias = "fileID", deserialize_with = "deserialize_file_id")]
     pub file_id: i64,
     #[serde(default, deserialize_with = "deserialize_option_string_or_float")]
     pub guid: Option<String>,
@@ -51,3 +51,29 @@ where
 
     deserializer.deserialize_any(StringOrFloat)
 }
+
+/// File IDs are signed, values written as unsigned 64-bit numbers keep their bits.
+fn deserialize_file_id<'de, D>(deserializer: D) -> Result<i64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct FileId;
+
+    impl Visitor<'_> for FileId {
+        type Value = i64;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("a file ID")
+        }
+
+        fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+            Ok(value)
+        }
+
+        fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+            Ok(value as i64)
+        }
+    }
+
+    deserializer.deserialize_any(FileId)
+}
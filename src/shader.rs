@@ -0,0 +1,246 @@
+use crate::asset::AssetType;
+use crate::convert::mapping::package_shader_name;
+use crate::package::Package;
+use crate::primitives::materials::{read_single_material, resolve_variants};
+use crate::primitives::reference::FileReference;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+
+/// GUID shared by the shaders built into Unity, they differ by file ID.
+pub const BUILTIN_SHADERS_GUID: &str = "0000000000000000f000000000000000";
+
+/// Built-in shaders by file ID.
+const BUILTIN_SHADERS: &[(i64, &str)] = &[
+    (7, "Legacy Shaders/Diffuse"),
+    (45, "Standard (Specular setup)"),
+    (46, "Standard"),
+    (106, "Skybox/Procedural"),
+    (10750, "Unlit/Transparent"),
+    (10751, "Unlit/Transparent Cutout"),
+    (10752, "Unlit/Texture"),
+    (10753, "Sprites/Default"),
+    (10755, "Unlit/Color"),
+    (10770, "UI/Default"),
+];
+
+/// Shaders of the render pipeline packages by GUID.
+const PIPELINE_SHADERS: &[(&str, &str, RenderPipeline)] = &[
+    (
+        "933532a4fcc9baf4fa0491de14d08ed7",
+        "Universal Render Pipeline/Lit",
+        RenderPipeline::Urp,
+    ),
+    (
+        "8d2bb70cbf9db8d4da26e15b26e74248",
+        "Universal Render Pipeline/Simple Lit",
+        RenderPipeline::Urp,
+    ),
+    (
+        "650dd9526735d5b46b79224bc6e94025",
+        "Universal Render Pipeline/Unlit",
+        RenderPipeline::Urp,
+    ),
+    (
+        "0ca6dca7396eb48e5849247ffd444914",
+        "Universal Render Pipeline/Baked Lit",
+        RenderPipeline::Urp,
+    ),
+    (
+        "b7839dad95683814aa64166edc107ae2",
+        "Universal Render Pipeline/Particles/Lit",
+        RenderPipeline::Urp,
+    ),
+    (
+        "0406db5a14f94604a8c57ccfbc9f3b46",
+        "Universal Render Pipeline/Particles/Unlit",
+        RenderPipeline::Urp,
+    ),
+    (
+        "6e4ae4064600d784cac1e41a9e6f2e59",
+        "HDRP/Lit",
+        RenderPipeline::Hdrp,
+    ),
+    (
+        "c4edd00ff2db5b24391a4fcb1762e459",
+        "HDRP/Unlit",
+        RenderPipeline::Hdrp,
+    ),
+];
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum RenderPipeline {
+    BuiltIn,
+    Urp,
+    Hdrp,
+    /// Shaders included in the package or coming from other packages.
+    Custom,
+}
+
+impl fmt::Display for RenderPipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RenderPipeline::BuiltIn => "Built-in",
+            RenderPipeline::Urp => "URP",
+            RenderPipeline::Hdrp => "HDRP",
+            RenderPipeline::Custom => "custom",
+        })
+    }
+}
+
+/// Shader referenced by a material.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ShaderInfo {
+    /// `None` for shaders that are neither known nor included in the package.
+    pub name: Option<String>,
+    pub pipeline: RenderPipeline,
+}
+
+impl ShaderInfo {
+    /// Resolves the material `m_Shader` reference, `package_shaders` are names of the package shaders by GUID.
+    pub fn resolve(reference: &FileReference, package_shaders: &HashMap<String, String>) -> Self {
+        let guid = reference.guid.as_deref().unwrap_or_default();
+        if guid == BUILTIN_SHADERS_GUID {
+            let name = BUILTIN_SHADERS
+                .iter()
+                .find(|(file_id, _)| *file_id == reference.file_id)
+                .map(|(_, name)| name.to_string());
+            return ShaderInfo {
+                name,
+                pipeline: RenderPipeline::BuiltIn,
+            };
+        }
+        if let Some((_, name, pipeline)) = PIPELINE_SHADERS.iter().find(|(g, _, _)| *g == guid) {
+            return ShaderInfo {
+                name: Some(name.to_string()),
+                pipeline: *pipeline,
+            };
+        }
+        ShaderInfo {
+            name: package_shaders.get(guid).cloned(),
+            pipeline: RenderPipeline::Custom,
+        }
+    }
+}
+
+/// Shaders used by the package materials.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ShaderReport {
+    pub materials: Vec<MaterialShader>,
+    /// Number of materials by pipeline.
+    pub pipelines: BTreeMap<RenderPipeline, usize>,
+    /// Pipeline used by most of the materials, custom shaders are counted only when there is nothing else.
+    pub pipeline: Option<RenderPipeline>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MaterialShader {
+    pub pathname: String,
+    pub guid: String,
+    pub shader: FileReference,
+    #[serde(flatten)]
+    pub info: ShaderInfo,
+}
+
+impl ShaderReport {
+    pub fn new(package: &Package) -> Result<ShaderReport> {
+        let mut package_shaders = HashMap::new();
+        let mut material_assets = vec![];
+        let mut materials = HashMap::new();
+        package.for_each_asset(
+            |a| a.asset_type == AssetType::Material || is_shader(&a.pathname),
+            |asset, reader| {
+                let mut content = String::new();
+                reader.read_to_string(&mut content)?;
+                if asset.asset_type != AssetType::Material {
+                    if let Some(name) = package_shader_name(&asset.pathname, Some(&content)) {
+                        package_shaders.insert(asset.guid.clone(), name);
+                    }
+                    return Ok(());
+                }
+                match read_single_material(&content) {
+                    Ok(material) => {
+                        materials.insert(asset.guid.clone(), material);
+                        material_assets.push(asset.clone());
+                    }
+                    Err(e) => eprintln!("Failed to read material {}: {}", asset.pathname, e),
+                }
+                Ok(())
+            },
+        )?;
+
+        for asset in package.assets().iter().filter(|a| is_shader(&a.pathname)) {
+            if let Some(name) = package_shader_name(&asset.pathname, None) {
+                package_shaders.entry(asset.guid.clone()).or_insert(name);
+            }
+        }
+        // Material Variants without own shader use the one of their parents
+        resolve_variants(&mut materials);
+
+        let mut report = ShaderReport::default();
+        for asset in material_assets {
+            let shader = materials[&asset.guid].shader.clone();
+            let info = ShaderInfo::resolve(&shader, &package_shaders);
+            *report.pipelines.entry(info.pipeline).or_default() += 1;
+            report.materials.push(MaterialShader {
+                pathname: asset.pathname,
+                guid: asset.guid,
+                shader,
+                info,
+            });
+        }
+        report.materials.sort_by(|a, b| a.pathname.cmp(&b.pathname));
+        report.pipeline = report
+            .pipelines
+            .iter()
+            .filter(|(p, _)| **p != RenderPipeline::Custom)
+            .max_by_key(|(_, count)| **count)
+            .or(report.pipelines.iter().next())
+            .map(|(p, _)| *p);
+        Ok(report)
+    }
+}
+
+impl fmt::Display for ShaderReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for material in &self.materials {
+            let name = match &material.info.name {
+                Some(name) => name.clone(),
+                None => format!(
+                    "{}:{}",
+                    material.shader.guid.as_deref().unwrap_or_default(),
+                    material.shader.file_id
+                ),
+            };
+            writeln!(
+                f,
+                "{} [{}] {}",
+                material.pathname, material.info.pipeline, name
+            )?;
+        }
+        let pipelines: Vec<String> = self
+            .pipelines
+            .iter()
+            .map(|(pipeline, count)| format!("{} {}", count, pipeline))
+            .collect();
+        match self.pipeline {
+            Some(pipeline) => writeln!(
+                f,
+                "Render pipeline: {} ({} materials: {})",
+                pipeline,
+                self.materials.len(),
+                pipelines.join(", ")
+            ),
+            None => writeln!(f, "Render pipeline: unknown, no materials"),
+        }
+    }
+}
+
+/// `.shader` and `.shadergraph` assets.
+pub(crate) fn is_shader(pathname: &str) -> bool {
+    let extension = Path::new(pathname).extension().and_then(|e| e.to_str());
+    matches!(extension, Some("shader" | "shadergraph"))
+}
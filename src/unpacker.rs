@@ -1,68 +1,181 @@
+use crate::archive::{self, EntryKind};
 use crate::asset::{Asset, AssetType};
-use flate2::read::GzDecoder;
-use gltf::{json, Document};
+use crate::convert::mapping::{package_shader_name, ShaderMappings};
+use crate::convert::material::{apply_materials, MaterialLibrary, MeshMaterials};
+use crate::convert::scene::{ModelSource, PrefabExporter};
+use crate::filter::AssetFilter;
+use crate::material_export::MaterialExport;
+use crate::primitives::materials::{read_single_material, resolve_variants, UnityMaterial};
+use crate::shader::is_shader;
+use anyhow::{bail, Context};
 use rayon::prelude::*;
-use std::borrow::Cow;
-
-use std::fs::File;
-use std::path::Path;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::sync::mpsc::channel;
-use std::sync::Arc;
 use std::{fs, io};
-use tar::Archive;
+
+/// Directory inside the output used for asset bodies that arrive before their `pathname` entry.
+const STAGING_DIR_NAME: &str = ".unpack_staging";
+/// Material library written into the output directory with `export_materials`.
+pub(crate) const MATERIALS_FILE_NAME: &str = "materials.json";
+
+/// Settings used by [`Unpacker`].
+#[derive(Clone, Debug, Default)]
+pub struct UnpackOptions {
+    /// path to the tool that will auto convert fbx files to gltf during unpacking
+    pub fbx_to_gltf: Option<PathBuf>,
+    /// override model textures with the ones from materials used in prefabs, requires `fbx_to_gltf`
+    pub get_materials_from_prefabs: bool,
+    /// assets that are not matching the filter are skipped
+    pub filter: AssetFilter,
+    /// copy meta files alongside regular files
+    pub copy_meta_files: bool,
+    /// material properties used for glTF materials, by shader
+    pub shader_mappings: ShaderMappings,
+    /// write `materials.json` with all the package materials into the output directory
+    pub export_materials: bool,
+    /// export prefabs as glb scenes next to them, requires `fbx_to_gltf`
+    pub export_prefabs: bool,
+}
 
 #[derive(Clone)]
 pub struct Unpacker {
-    pub args: crate::args::Args,
+    pub output_dir: PathBuf,
+    pub options: UnpackOptions,
     pub assets: Vec<Asset>,
+    /// Pathnames of all the package entries by GUID, including the ones skipped by the filter.
+    pub pathnames: HashMap<String, String>,
 }
 
 impl Unpacker {
-    pub fn prepare_environment(&self) {
-        self.args.check();
-        let archive_path = Path::new(&self.args.input);
-        let output_dir = Path::new(&self.args.output);
-        let tmp_path = Path::new("./tmp_dir");
-        if !archive_path.exists() {
-            panic!("Input file does not exits");
-        }
-        if tmp_path.exists() {
-            println!("Temp directory exits, cleaning up first.");
-            fs::remove_dir_all(tmp_path).unwrap();
-        }
-        if output_dir.exists() {
-            println!("Output directory exits, cleaning up first.");
-            fs::remove_dir_all(output_dir).unwrap();
+    pub fn new(output_dir: impl Into<PathBuf>, options: UnpackOptions) -> Unpacker {
+        Unpacker {
+            output_dir: output_dir.into(),
+            options,
+            assets: vec![],
+            pathnames: HashMap::new(),
         }
     }
 
-    pub fn extract(&mut self) {
-        let archive_path = Path::new(&self.args.input);
-        let tmp_path = Path::new("./tmp_dir");
-        let output_dir = Path::new(&self.args.output);
+    /// Extracts the archive and runs all the enabled post processing steps.
+    pub fn unpack(&mut self, archive_path: &Path) -> io::Result<()> {
+        self.extract(archive_path)?;
+        self.process_data();
+        self.update_gltf_materials();
+        self.export_prefab_scenes();
+        self.export_materials()?;
+        Ok(())
+    }
 
-        if let Err(e) = Unpacker::extract_archive(archive_path, tmp_path) {
-            println!("Failed to extract archive: {}", e);
+    /// Removes the output directory if it exists and creates an empty one.
+    pub fn prepare_environment(&self) -> io::Result<()> {
+        if self.output_dir.exists() {
+            println!("Output directory exits, cleaning up first.");
+            fs::remove_dir_all(&self.output_dir)?;
         }
+        fs::create_dir_all(&self.output_dir)
+    }
+
+    pub fn extract(&mut self, archive_path: &Path) -> io::Result<()> {
+        (self.assets, self.pathnames) = self.extract_archive(archive_path)?;
+        Ok(())
+    }
 
-        let (sender, receiver) = channel();
-        let ignored_extensions = self.args.clone().ignore_extensions.unwrap_or_default();
+    /// Streams the archive entries once, writing asset bodies straight to their final path.
+    ///
+    /// Entries of a single GUID can come in any order, so an `asset` that shows up before its
+    /// `pathname` is parked in a staging directory inside the output and moved once the path is known.
+    fn extract_archive(
+        &self,
+        archive_path: &Path,
+    ) -> io::Result<(Vec<Asset>, HashMap<String, String>)> {
+        let output_dir = self.output_dir.as_path();
+        let staging_dir = output_dir.join(STAGING_DIR_NAME);
+        let mut pending: HashMap<String, PendingAsset> = HashMap::new();
 
-        fs::read_dir(tmp_path)
-            .unwrap()
-            .par_bridge()
-            .for_each_with(sender, |s, entry| {
-                let entry = entry.unwrap();
-                let asset = crate::asset::Asset::from_path(&entry, output_dir);
-                if let Some(asset) = asset {
-                    let extension = &asset.extension.clone().unwrap_or_default();
-                    if !ignored_extensions.contains(extension) {
-                        s.send(asset).unwrap();
+        let mut archive = archive::open(archive_path)?;
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let entry_path = entry.path()?.into_owned();
+            let Some((guid, kind)) = archive::split_entry_path(&entry_path) else {
+                continue;
+            };
+            let item = pending.entry(guid.clone()).or_default();
+            match kind {
+                EntryKind::Pathname => {
+                    let Some(pathname) = archive::read_pathname(&mut entry)? else {
+                        continue;
+                    };
+                    let asset = Asset::new(guid, pathname);
+                    item.ignored = !self.options.filter.matches(&asset);
+                    if let Some(staged) = item.staged_asset.take() {
+                        if item.ignored {
+                            fs::remove_file(staged)?;
+                        } else {
+                            asset.prepare_directory(output_dir);
+                            fs::rename(staged, asset.output_path(output_dir))?;
+                        }
                     }
+                    item.asset = Some(asset);
                 }
-            });
-        self.assets = receiver.iter().collect();
+                EntryKind::Asset => {
+                    item.size = Some(entry.size());
+                    match &item.asset {
+                        Some(_) if item.ignored => {}
+                        Some(asset) => {
+                            asset.prepare_directory(output_dir);
+                            entry.unpack(asset.output_path(output_dir))?;
+                        }
+                        None => {
+                            fs::create_dir_all(&staging_dir)?;
+                            let staged = staging_dir.join(&guid);
+                            entry.unpack(&staged)?;
+                            item.staged_asset = Some(staged);
+                        }
+                    }
+                }
+                EntryKind::Meta => {
+                    let mut meta = Vec::new();
+                    entry.read_to_end(&mut meta)?;
+                    item.meta = Some(meta);
+                }
+                _ => continue,
+            }
+        }
+
+        let pathnames = pending
+            .iter()
+            .filter_map(|(guid, item)| Some((guid.clone(), item.asset.as_ref()?.pathname.clone())))
+            .collect();
+        let mut assets = Vec::new();
+        for (guid, item) in pending {
+            if let Some(staged) = item.staged_asset {
+                eprintln!("{}: asset without pathname, skipping", guid);
+                fs::remove_file(staged)?;
+                continue;
+            }
+            let (Some(mut asset), Some(size)) = (item.asset, item.size) else {
+                continue;
+            };
+            if item.ignored {
+                continue;
+            }
+            asset.size = size;
+            if let Some(meta) = &item.meta {
+                asset.set_meta(&String::from_utf8_lossy(meta));
+                if self.options.copy_meta_files {
+                    let mut meta_path = asset.output_path(output_dir).into_os_string();
+                    meta_path.push(".meta");
+                    fs::write(meta_path, meta)?;
+                }
+            }
+            assets.push(asset);
+        }
+        if staging_dir.exists() {
+            fs::remove_dir_all(staging_dir)?;
+        }
+        Ok((assets, pathnames))
     }
 
     pub fn assets_of_type(&self, asset_type: AssetType) -> Vec<Asset> {
@@ -73,164 +186,282 @@ impl Unpacker {
             .collect()
     }
 
+    /// Unpacked asset paths by GUID.
+    fn asset_paths(&self) -> HashMap<String, PathBuf> {
+        self.assets
+            .iter()
+            .map(|a| (a.guid.clone(), a.output_path(&self.output_dir)))
+            .collect()
+    }
+
+    /// Parsed materials by GUID with Material Variants merged with their parents, files that fail
+    /// to parse are reported and skipped.
+    fn read_materials(&self) -> HashMap<String, UnityMaterial> {
+        let mut materials = self
+            .assets_of_type(AssetType::Material)
+            .par_iter()
+            .filter_map(|material| {
+                let path = material.output_path(&self.output_dir);
+                match fs::read_to_string(path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|content| read_single_material(&content))
+                {
+                    Ok(unity_material) => Some((material.guid.clone(), unity_material)),
+                    Err(e) => {
+                        eprintln!("Failed to read material {}: {}", material.pathname, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+        resolve_variants(&mut materials);
+        materials
+    }
+
+    /// Names of the shaders included in the package by GUID, shaders that weren't extracted are
+    /// named after their files.
+    fn shader_names(&self) -> HashMap<String, String> {
+        self.pathnames
+            .iter()
+            .filter(|(_, pathname)| is_shader(pathname))
+            .filter_map(|(guid, pathname)| {
+                let source = fs::read_to_string(self.output_dir.join(pathname)).ok();
+                Some((
+                    guid.clone(),
+                    package_shader_name(pathname, source.as_deref())?,
+                ))
+            })
+            .collect()
+    }
+
+    /// Materials, textures and shader mappings used for converting the materials.
+    pub fn material_library(&self) -> MaterialLibrary {
+        MaterialLibrary {
+            materials: self.read_materials(),
+            asset_paths: self.asset_paths(),
+            shader_names: self.shader_names(),
+            mappings: self.options.shader_mappings.clone(),
+        }
+    }
+
+    /// Writes `materials.json` when `export_materials` is enabled.
+    pub fn export_materials(&self) -> io::Result<()> {
+        if !self.options.export_materials {
+            return Ok(());
+        }
+        let export = MaterialExport::new(&self.material_library(), &self.pathnames);
+        let path = self.output_dir.join(MATERIALS_FILE_NAME);
+        fs::write(&path, serde_json::to_string_pretty(&export)?)?;
+        println!(
+            "{} materials written to {}",
+            export.materials.len(),
+            path.display()
+        );
+        Ok(())
+    }
+
+    fn report_unmapped_shaders(&self, library: &MaterialLibrary) {
+        let unmapped = library.unmapped();
+        if unmapped.is_empty() {
+            return;
+        }
+        eprintln!(
+            "{} materials use shaders without mapping, default property names are used:",
+            unmapped.len()
+        );
+        for guid in unmapped {
+            let unity_material = &library.materials[guid];
+            let pathname = self
+                .assets
+                .iter()
+                .find(|a| a.guid == guid)
+                .map(|a| a.pathname.as_str())
+                .unwrap_or(guid);
+            let shader = match library.shader(unity_material).name {
+                Some(name) => name,
+                None => format!(
+                    "{}:{}",
+                    unity_material.shader.guid.as_deref().unwrap_or_default(),
+                    unity_material.shader.file_id
+                ),
+            };
+            eprintln!("  {} ({})", pathname, shader);
+        }
+    }
+
+    /// Applies materials to the converted models.
+    ///
+    /// Material remaps from the model meta files are always used, prefab renderers override them
+    /// when `get_materials_from_prefabs` is enabled.
     pub fn update_gltf_materials(&self) {
-        if self.args.fbx_to_gltf.is_none() || !self.args.get_materials_from_prefabs {
+        if self.options.fbx_to_gltf.is_none() {
             return;
         }
         let fbx_models = self.assets_of_type(AssetType::FbxModel);
-        let prefabs = self.assets_of_type(AssetType::Prefab);
-        let materials = self.assets_of_type(AssetType::Material);
+        let prefabs = if self.options.get_materials_from_prefabs {
+            self.assets_of_type(AssetType::Prefab)
+        } else {
+            vec![]
+        };
+        let library = self.material_library();
         println!(
             "There are {} models, {} prefabs and {} materials",
             fbx_models.len(),
             prefabs.len(),
-            materials.len()
+            library.materials.len()
         );
+        self.report_unmapped_shaders(&library);
 
-        prefabs.par_iter().for_each(|prefab| {
-            let path = Path::new(&prefab.path);
-            let prefab_content = fs::read_to_string(path).unwrap();
-            let matching_materials: Vec<Asset> = materials
-                .clone()
-                .into_iter()
-                .filter(|a| prefab_content.contains(&a.guid))
-                .collect();
-            let matching_models: Vec<Asset> = fbx_models
-                .clone()
-                .into_iter()
-                .filter(|a| prefab_content.contains(&a.guid))
-                .collect();
-            if matching_materials.len() != 1 || 1 != matching_models.len() {
-                return;
+        let prefab_meshes: Vec<Vec<MeshMaterials>> = prefabs
+            .par_iter()
+            .filter_map(|prefab| {
+                let path = prefab.output_path(&self.output_dir);
+                match fs::read_to_string(path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|content| MeshMaterials::from_prefab(&content))
+                {
+                    Ok(meshes) => Some(meshes),
+                    Err(e) => {
+                        eprintln!("Failed to read prefab {}: {}", prefab.pathname, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+        // the first prefab using a mesh decides about its materials
+        let mut model_meshes: HashMap<String, Vec<MeshMaterials>> = HashMap::new();
+        for mesh in prefab_meshes.into_iter().flatten() {
+            let meshes = model_meshes.entry(mesh.model_guid.clone()).or_default();
+            if !meshes.iter().any(|m| m.mesh_file_id == mesh.mesh_file_id) {
+                meshes.push(mesh);
             }
-            let material = matching_materials.first().unwrap();
-            let model: &Asset = matching_models.first().unwrap();
-            let texture_guid: Option<String> = material.try_get_mat_texture_guid();
+        }
 
-            let texture_asset: &Asset = match &texture_guid {
-                Some(guid) => self.assets.iter().find(|a| guid.eq(&a.guid)).unwrap(),
-                None => return,
-            };
-            // here we should read gltf file and replace material texture with Uri based on texture_asset
-            let model_path = Path::new(&model.path).with_extension("glb");
-            Self::update_material(&model_path, Path::new(&texture_asset.path));
+        fbx_models.par_iter().for_each(|model| {
+            let importer = model.meta.as_ref().and_then(|m| m.model_importer.as_ref());
+            let remaps = importer.map(|i| i.material_remaps()).unwrap_or_default();
+            let meshes = model_meshes
+                .get(&model.guid)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            if meshes.is_empty() && remaps.is_empty() {
+                return;
+            }
+            let mesh_names = importer.map(|i| i.object_names()).unwrap_or_default();
+            let model_path = model.glb_path(&self.output_dir);
+            match apply_materials(&model_path, meshes, &mesh_names, &remaps, &library) {
+                Ok(()) => println!("Materials applied to {}", model_path.display()),
+                Err(e) => eprintln!("Failed to update {}: {:#}", model_path.display(), e),
+            }
         });
     }
 
-    fn align_to_multiple_of_four(n: &mut usize) {
-        *n = (*n + 3) & !3;
-    }
-
-    fn update_material(gltf_path: &Path, texture_asset: &Path) {
-        let file = fs::File::open(gltf_path).unwrap();
-        let reader = io::BufReader::new(file);
-        let mut gltf = gltf::Gltf::from_reader(reader).unwrap();
-        let mut json = gltf.document.into_json();
-        for image in json.images.iter_mut() {
-            let result = texture_asset
-                .file_name()
-                .unwrap()
-                .to_str()
-                .unwrap()
-                .to_string();
-            let required_file = gltf_path.with_file_name(&result);
-            if !required_file.exists() {
-                fs::copy(texture_asset, gltf_path.with_file_name(&result)).unwrap();
-            }
-            if let Some(old_path) = &image.uri {
-                if old_path.eq(&result) {
-                    return;
+    /// Writes `<name>.prefab.glb` next to every prefab when `export_prefabs` is enabled.
+    ///
+    /// Meshes are taken from the converted models, so it should run after `update_gltf_materials`.
+    /// The `.prefab` part keeps the files apart from the models converted from `<name>.fbx`.
+    pub fn export_prefab_scenes(&self) {
+        if !self.options.export_prefabs {
+            return;
+        }
+        if self.options.fbx_to_gltf.is_none() {
+            eprintln!("Prefabs are not exported, exporting them requires fbx_to_gltf");
+            return;
+        }
+        let library = self.material_library();
+        let models: HashMap<String, ModelSource> = self
+            .assets_of_type(AssetType::FbxModel)
+            .into_iter()
+            .filter_map(|model| {
+                let glb_path = model.glb_path(&self.output_dir);
+                if !glb_path.exists() {
+                    return None;
                 }
+                let importer = model.meta.as_ref().and_then(|m| m.model_importer.as_ref());
+                let source = ModelSource {
+                    glb_path,
+                    object_names: importer.map(|i| i.object_names()).unwrap_or_default(),
+                    remaps: importer.map(|i| i.material_remaps()).unwrap_or_default(),
+                };
+                Some((model.guid, source))
+            })
+            .collect();
+        let prefabs = self.assets_of_type(AssetType::Prefab);
+        let prefab_paths: HashMap<String, PathBuf> = prefabs
+            .iter()
+            .map(|prefab| (prefab.guid.clone(), prefab.output_path(&self.output_dir)))
+            .collect();
+        let exporter = PrefabExporter::new(&library, &models, &prefab_paths);
+
+        prefabs.par_iter().for_each(|prefab| {
+            let prefab_path = prefab.output_path(&self.output_dir);
+            let glb_path = prefab_path.with_extension("prefab.glb");
+            match exporter.export(&prefab_path, &glb_path) {
+                Ok(()) => println!("Prefab exported to {}", glb_path.display()),
+                Err(e) => eprintln!("Failed to export prefab {}: {:#}", prefab.pathname, e),
             }
-            println!(
-                "Image{:?}: {:?} to be replaced with: {}",
-                image.name, image.uri, &result
-            );
-            image.uri = Some(result);
-        }
-
-        gltf.document = Document::from_json(json.clone()).unwrap();
-        // Save the modified glTF
-        let json_string = json::serialize::to_string(&json).expect("Serialization error");
-        let mut json_offset = json_string.len();
-        Self::align_to_multiple_of_four(&mut json_offset);
-        let blob = gltf.blob.clone().unwrap_or_default();
-        let buffer_length = blob.len();
-        let glb = gltf::binary::Glb {
-            header: gltf::binary::Header {
-                magic: *b"glTF",
-                version: 2,
-                // N.B., the size of binary glTF file is limited to range of `u32`.
-                length: (json_offset + buffer_length)
-                    .try_into()
-                    .expect("file size exceeds binary glTF limit"),
-            },
-            bin: Some(Cow::Owned(gltf.blob.unwrap_or_default())),
-            json: Cow::Owned(json_string.into_bytes()),
-        };
-        let writer = std::fs::File::create(gltf_path).expect("I/O error");
-        glb.to_writer(writer).expect("glTF binary output error");
+        });
     }
 
     pub fn process_data(&self) {
-        let output_dir = Path::new(&self.args.output);
-        let copy_meta_files = self.args.copy_meta_files;
-        let tmp_path = Path::new("./tmp_dir");
-
-        let tmp_dir = Arc::new(tmp_path);
-        fs::create_dir(output_dir).unwrap();
-
-        self.assets.par_iter().for_each(|asset| {
-            let asset_hash = &asset.guid;
-            let path = Path::new(&asset.path);
-            let source_asset = Path::new(&*tmp_dir).join(asset_hash).join("asset");
-
-            asset.prepare_directory();
-            if copy_meta_files && asset.has_meta {
-                let source_meta = Path::new(&*tmp_dir).join(asset_hash).join("asset.meta");
-                let mut meta_path = asset.path.clone();
-                meta_path.push_str(".meta");
-                fs::rename(source_meta, meta_path).unwrap();
-            }
-
-            if !source_asset.exists() {
-                panic!("SOURCE ASSET DOES NOT EXIST: {}", source_asset.display());
-            }
-
-            if self.args.fbx_to_gltf.is_some() && asset.asset_type == AssetType::FbxModel {
-                self.process_fbx_file(&source_asset, path);
-            } else {
-                fs::rename(source_asset, path).unwrap();
-            }
-        });
+        if self.options.fbx_to_gltf.is_none() {
+            return;
+        }
 
-        fs::remove_dir_all(Path::new(&*tmp_dir)).unwrap();
+        self.assets_of_type(AssetType::FbxModel)
+            .par_iter()
+            .for_each(|asset| {
+                let path = asset.output_path(&self.output_dir);
+                let glb_path = asset.glb_path(&self.output_dir);
+                // the fbx is kept when the conversion fails, it is the only copy of the model
+                match self.process_fbx_file(&path, &glb_path) {
+                    Ok(()) => {
+                        println!("Fbx converted to GLTF: {}", glb_path.display());
+                        if let Err(e) = fs::remove_file(&path) {
+                            eprintln!("Failed to remove {}: {}", path.display(), e);
+                        }
+                    }
+                    Err(e) => eprintln!("Failed to convert {}: {:#}", asset.pathname, e),
+                }
+            });
     }
 
-    fn process_fbx_file(&self, source_asset: &Path, result_path: &Path) {
-        let tool = self.args.fbx_to_gltf.clone().unwrap();
-        let out_path = result_path.with_extension("");
-        let _output = Command::new(tool)
-            .args([
-                "--input",
-                source_asset.to_str().unwrap(),
-                "-b",
-                "--output",
-                out_path.to_str().unwrap(),
-            ])
+    /// Runs `fbx_to_gltf`, converting `source_asset` into `glb_path`.
+    fn process_fbx_file(&self, source_asset: &Path, glb_path: &Path) -> anyhow::Result<()> {
+        let tool = self
+            .options
+            .fbx_to_gltf
+            .as_ref()
+            .context("no fbx_to_gltf")?;
+        let output = Command::new(tool)
+            .arg("--input")
+            .arg(source_asset)
+            .arg("-b")
+            .arg("--output")
+            .arg(glb_path)
             .output()
-            .unwrap();
-        println!(
-            "Fbx converted to GLTF: {}",
-            out_path.with_extension("glb").to_str().unwrap()
-        );
-    }
-
-    fn extract_archive(archive_path: &Path, extract_to: &Path) -> io::Result<()> {
-        let tar_gz = File::open(archive_path)?;
-        let tar = GzDecoder::new(tar_gz);
-        let mut archive = Archive::new(tar);
-        archive.unpack(extract_to)?;
+            .with_context(|| format!("failed to run {}", tool.display()))?;
+        if !output.status.success() {
+            bail!(
+                "{} exited with {}: {}",
+                tool.display(),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        if !glb_path.exists() {
+            bail!("{} was not created", glb_path.display());
+        }
         Ok(())
     }
 }
+
+/// Entries collected so far for a single GUID directory of the archive.
+#[derive(Default)]
+struct PendingAsset {
+    asset: Option<Asset>,
+    ignored: bool,
+    size: Option<u64>,
+    staged_asset: Option<PathBuf>,
+    meta: Option<Vec<u8>>,
+}
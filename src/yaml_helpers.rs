@@ -1,41 +1,234 @@
-use anyhow::Result;
+use crate::primitives::class_id::{class_name, UnityClass};
+use anyhow::{bail, Context, Result};
+use regex::Regex;
 use serde::de::DeserializeOwned;
-use std::collections::HashMap;
-
-pub fn parse_unity_yaml<T: DeserializeOwned>(file: &str) -> Result<HashMap<i64, T>> {
-    let file = cleanup_unity_yaml(file)?;
-    let parse: HashMap<i64, T> = serde_yaml::from_str(&file)?;
-
-    Ok(parse)
-}
-
-fn cleanup_unity_yaml(yaml: &str) -> Result<String> {
-    let lines: Vec<String> = yaml
-        .lines()
-        .filter_map(|line| {
-            if line.starts_with("%YAML") || line.starts_with("%TAG") {
-                // unity specific headers. SKIP!
-                None
-            } else if line.starts_with("--- !u!") {
-                // unity object id declared on this line
-                // --- !u!104 &2 => 104 is object type and 2 is object id
-                let mut splits = line.split_whitespace();
-                let object_id: i64 = splits
-                    .find(|&part| part.starts_with('&'))
-                    .and_then(|num| num[1..].parse().ok())?;
-
-                Some(format!("{}:", object_id))
-            } else if line.starts_with(' ') {
-                Some(line.to_string())
-            } else {
-                Some(format!("  object_type: {}", line.replace(':', "")))
+use serde_yaml::Value;
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+/// Single object of Unity YAML file (scene, prefab, material, etc.).
+///
+/// Every object starts with `--- !u!<class ID> &<file ID>` header, optionally followed by `stripped`,
+/// and has a body with a single top-level key naming its type, e.g. `GameObject:`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnityDocument {
+    pub class_id: u32,
+    pub file_id: i64,
+    /// Objects of the nested prefabs that are only placeholders for references, with almost empty body.
+    pub stripped: bool,
+    pub type_name: String,
+    /// Body of the object under the type name key, `Null` when the body is empty.
+    pub value: Value,
+}
+
+impl UnityDocument {
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T> {
+        serde_yaml::from_value(self.value.clone())
+            .with_context(|| format!("invalid {} &{}", self.type_name, self.file_id))
+    }
+
+    /// Field of the object body.
+    pub fn get(&self, field: &str) -> Option<&Value> {
+        self.value.get(field)
+    }
+
+    /// Registered name of the class, `None` for class IDs unknown to this crate.
+    pub fn class_name(&self) -> Option<&'static str> {
+        class_name(self.class_id)
+    }
+
+    /// Typed object, `None` when the document has a different class.
+    pub fn object<T: UnityClass + DeserializeOwned>(&self) -> Option<Result<T>> {
+        (self.class_id == T::CLASS_ID).then(|| self.deserialize())
+    }
+}
+
+/// First object of the `T` class.
+pub fn find_object<T: UnityClass + DeserializeOwned>(
+    documents: &[UnityDocument],
+) -> Option<Result<T>> {
+    documents.iter().find_map(UnityDocument::object)
+}
+
+/// Objects of Unity YAML file, malformed objects are reported apart so the others stay usable.
+#[derive(Debug, Default)]
+pub struct UnityDocuments {
+    /// Parsed objects, in the file order.
+    pub documents: Vec<UnityDocument>,
+    /// Errors of the objects that could not be parsed.
+    pub errors: Vec<anyhow::Error>,
+}
+
+/// Splits Unity YAML file into objects, in the file order.
+///
+/// Lines before the first object header (`%YAML` and `%TAG` directives) are skipped.
+pub fn parse_unity_documents(yaml: &str) -> UnityDocuments {
+    let mut parsed = UnityDocuments::default();
+    let mut push = |header: &str, body: &str| match parse_document(header, body) {
+        Ok(document) => parsed.documents.push(document),
+        Err(e) => parsed.errors.push(e),
+    };
+    let mut header: Option<&str> = None;
+    let mut body = String::new();
+    for line in yaml.lines() {
+        if line.starts_with("--- ") || line == "---" {
+            if let Some(header) = header {
+                push(header, &body);
             }
-        })
-        .collect();
+            header = Some(line);
+            body.clear();
+        } else if header.is_some() {
+            body.push_str(line);
+            body.push('\n');
+        }
+    }
+    if let Some(header) = header {
+        push(header, &body);
+    }
+    parsed
+}
+
+fn parse_document(header: &str, body: &str) -> Result<UnityDocument> {
+    let mut class_id = None;
+    let mut file_id = None;
+    let mut stripped = false;
+    for part in header.split_whitespace().skip(1) {
+        if let Some(id) = part.strip_prefix("!u!") {
+            class_id = id.parse().ok();
+        } else if let Some(id) = part.strip_prefix('&') {
+            file_id = parse_file_id(id);
+        } else if part == "stripped" {
+            stripped = true;
+        }
+    }
+    let (Some(class_id), Some(file_id)) = (class_id, file_id) else {
+        bail!("invalid object header: {}", header);
+    };
+    let content: Value = serde_yaml::from_str(&quote_guids(body))
+        .with_context(|| format!("invalid object &{}", file_id))?;
+    let (type_name, value) = match content {
+        Value::Mapping(mapping) => match mapping.into_iter().next() {
+            Some((Value::String(type_name), value)) => (type_name, value),
+            _ => bail!("object &{} has no type", file_id),
+        },
+        _ => bail!("object &{} has no type", file_id),
+    };
+    Ok(UnityDocument {
+        class_id,
+        file_id,
+        stripped,
+        type_name,
+        value,
+    })
+}
+
+/// File IDs are signed 64-bit numbers, values written as unsigned keep their bits.
+fn parse_file_id(id: &str) -> Option<i64> {
+    id.parse::<i64>()
+        .ok()
+        .or_else(|| id.parse::<u64>().ok().map(|id| id as i64))
+}
+
+/// Wraps GUID values in quotes, otherwise GUIDs made only from digits are parsed as numbers.
+pub fn quote_guids(yaml: &str) -> Cow<'_, str> {
+    static GUID_REGEX: OnceLock<Regex> = OnceLock::new();
+    let regex = GUID_REGEX.get_or_init(|| Regex::new(r"(guid: )([0-9a-fA-F]{32})\b").unwrap());
+    regex.replace_all(yaml, "${1}\"${2}\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PREFAB: &str = "%YAML 1.1
+%TAG !u! tag:unity3d.com,2011:
+--- !u!1 &100000
+GameObject:
+  m_Name: Rock
+--- !u!4 &-4216859302048453862 stripped
+Transform:
+  m_CorrespondingSourceObject: {fileID: 400000, guid: 12345678901234567890123456789012, type: 3}
+  m_PrefabInstance: {fileID: 9000}
+--- !u!114 &11400000
+MonoBehaviour:
+--- !u!1001 &18446744073709551615
+PrefabInstance:
+  m_SourcePrefab: {fileID: 100100000, guid: 12345678901234567890123456789012, type: 3}
+";
+
+    #[test]
+    fn parses_headers_and_skips_preamble() {
+        let parsed = parse_unity_documents(PREFAB);
+        assert!(parsed.errors.is_empty());
+        let headers: Vec<(u32, i64, bool, &str)> = parsed
+            .documents
+            .iter()
+            .map(|d| (d.class_id, d.file_id, d.stripped, d.type_name.as_str()))
+            .collect();
+        assert_eq!(
+            headers,
+            [
+                (1, 100000, false, "GameObject"),
+                (4, -4216859302048453862, true, "Transform"),
+                (114, 11400000, false, "MonoBehaviour"),
+                (1001, -1, false, "PrefabInstance"),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_numeric_guids_as_strings() {
+        let parsed = parse_unity_documents(PREFAB);
+        let source = parsed.documents[1]
+            .get("m_CorrespondingSourceObject")
+            .and_then(|r| r.get("guid"));
+        assert_eq!(
+            source.and_then(Value::as_str),
+            Some("12345678901234567890123456789012")
+        );
+    }
+
+    #[test]
+    fn empty_body_is_null() {
+        let parsed = parse_unity_documents(PREFAB);
+        assert_eq!(parsed.documents[2].value, Value::Null);
+    }
 
-    let mut lines = lines.join("\n");
+    #[test]
+    fn parses_full_range_file_ids() {
+        assert_eq!(parse_file_id("-1"), Some(-1));
+        assert_eq!(parse_file_id("9223372036854775807"), Some(i64::MAX));
+        assert_eq!(parse_file_id("9223372036854775808"), Some(i64::MIN));
+        assert_eq!(parse_file_id("18446744073709551615"), Some(-1));
+        assert_eq!(parse_file_id("18446744073709551616"), None);
+        assert_eq!(parse_file_id("abc"), None);
+    }
 
-    lines.push('\n'); // insert new line at the end
+    #[test]
+    fn reports_malformed_documents() {
+        let yaml = "%YAML 1.1
+--- !u!1 &1
+GameObject:
+  m_Name: First
+--- !u!1 &2
+--- !u!4 &3
+Transform: {m_Father: [
+--- !u!4 &
+Transform: {}
+--- !u!1 &4
+GameObject:
+  m_Name: Last
+";
+        let parsed = parse_unity_documents(yaml);
+        let file_ids: Vec<i64> = parsed.documents.iter().map(|d| d.file_id).collect();
+        assert_eq!(file_ids, [1, 4]);
+        assert_eq!(parsed.errors.len(), 3);
+    }
 
-    Ok(lines)
+    #[test]
+    fn no_documents_without_headers() {
+        let parsed = parse_unity_documents("%YAML 1.1\n%TAG !u! tag:unity3d.com,2011:\n");
+        assert!(parsed.documents.is_empty());
+        assert!(parsed.errors.is_empty());
+    }
 }